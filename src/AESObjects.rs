@@ -5,35 +5,36 @@ use aes::cipher::{
     generic_array::GenericArray,
 };
 
+use zeroize::Zeroizing;
+
 use crate::Codec::CodecFunctions;
 use crate::HashHandling::{verify_hash, hash_key};
-use crate::{RZ_KEY_TYPE, KEY_LENGTH_BYTES, KEY_LENGTH_BITS};
+use crate::RZ_KEY_TYPE;
 
 impl CodecFunctions for AESEncoder
 {
-    fn encode(data: &Vec<u8>, key: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
+    fn encode(data: &[u8], key: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
     {
         // Generate validation hash (for key verification)
         let (validation_hash, key_ref): ([u8; 32], &RZ_KEY_TYPE) = hash_key(key);
         
-        // Use user key directly as AES key (i128 = 16 bytes, perfect for AES-128)
-        let aes_key: [u8; 16] = key_ref.to_be_bytes();
-        
-        // Create AES-128 cipher
-        let cipher = Aes128::new_from_slice(&aes_key)
+        // Use user key directly as AES key (16 bytes, perfect for AES-128)
+        // Create AES-128 cipher, whose expanded key is wiped on drop
+        let cipher = Aes128::new_from_slice(key_ref.as_bytes())
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Failed to create cipher: {}", e)))?;
         
         // Prepare data for encryption (padding if necessary)
-        let mut padded_data = data.clone();
+        // Plaintext copy is wiped once encrypted
+        let mut padded_data: Zeroizing<Vec<u8>> = Zeroizing::new(data.to_vec());
         let block_size = 16;
         let padding_needed = block_size - (data.len() % block_size);
         if padding_needed < block_size 
         {
-            padded_data.extend(std::iter::repeat(padding_needed as u8).take(padding_needed));
+            padded_data.extend(std::iter::repeat_n(padding_needed as u8, padding_needed));
         } 
         else 
         {
-            padded_data.extend(std::iter::repeat(block_size as u8).take(block_size));
+            padded_data.extend(std::iter::repeat_n(block_size as u8, block_size));
         }
         
         // Encrypt data block by block
@@ -53,7 +54,7 @@ impl CodecFunctions for AESEncoder
         return Ok(result);
     }
 
-    fn decode(encoded_data: &Vec<u8>, possible_key: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
+    fn decode(encoded_data: &[u8], possible_key: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
     { 
         if encoded_data.len() < 33 
         { 
@@ -62,6 +63,10 @@ impl CodecFunctions for AESEncoder
 
         let validation_hash = &encoded_data[..32];
         let encrypted_data = &encoded_data[32..];
+        if !encrypted_data.len().is_multiple_of(16)
+        {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Encrypted data is not a whole number of blocks: it is truncated"));
+        }
 
         let possible_key_ref: &RZ_KEY_TYPE = possible_key.expect("Error: No key");
         
@@ -72,10 +77,7 @@ impl CodecFunctions for AESEncoder
         }
 
         // Use user key directly as AES key (same process as encoding)
-        let aes_key: [u8; 16] = possible_key_ref.to_be_bytes();
-        
-        // Create AES-128 cipher
-        let cipher = Aes128::new_from_slice(&aes_key)
+        let cipher = Aes128::new_from_slice(possible_key_ref.as_bytes())
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Failed to create cipher: {}", e)))?;
         
         // Decrypt data block by block
@@ -89,11 +91,9 @@ impl CodecFunctions for AESEncoder
         
         // Remove padding
         if let Some(&padding_size) = decrypted_data.last() 
+            && padding_size as usize <= decrypted_data.len() && padding_size <= 16 
         {
-            if padding_size as usize <= decrypted_data.len() && padding_size <= 16 
-            {
-                decrypted_data.truncate(decrypted_data.len() - padding_size as usize);
-            }
+            decrypted_data.truncate(decrypted_data.len() - padding_size as usize);
         }
         
         return Ok(decrypted_data);
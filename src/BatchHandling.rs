@@ -0,0 +1,162 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use glob::Pattern;
+
+use crate::EnvHandling::STDIO_PATH;
+use crate::VolumeHandling::volume_base;
+
+// Which files are picked up inside directories; files named on the command line are always processed
+pub struct BatchOptions
+{
+    pub recursive: bool,
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
+}
+
+// What a command reads, so directories only yield the files it can process
+#[derive(Clone, Copy, PartialEq)]
+pub enum BatchInput
+{
+    Plain,
+    Encoded,
+}
+
+fn is_encoded_name(name: &str) -> bool
+{
+    return name.ends_with(".rsz") || volume_base(name) != name;
+}
+
+fn wanted(name: &str, input: BatchInput) -> bool
+{
+    match input
+    {
+        // Earlier results are not encoded again
+        BatchInput::Plain => { return !is_encoded_name(name); }
+        // Split archives are decoded once, through their first volume
+        BatchInput::Encoded => { return name.ends_with(".rsz") || (volume_base(name) != name && name.ends_with(".001")); }
+    }
+}
+
+fn matches_any(patterns: &[Pattern], name: &str, relative_path: &str) -> bool
+{
+    // Patterns with a / match the path below the directory given, the others only the name
+    return patterns.iter().any(|pattern| if pattern.as_str().contains('/') { pattern.matches(relative_path) } else { pattern.matches(name) });
+}
+
+fn walk_directory(directory: &Path, relative_path: &str, options: &BatchOptions, input: BatchInput, files: &mut Vec<String>, failures: &mut Vec<String>)
+{
+    let mut entries: Vec<fs::DirEntry> = match fs::read_dir(directory).and_then(|entries| entries.collect::<std::io::Result<Vec<fs::DirEntry>>>())
+    {
+        Ok(entries) => entries,
+        Err(e) =>
+        {
+            eprintln!("{} could not be read: {}", directory.display(), e);
+            failures.push(directory.to_string_lossy().into_owned());
+            return;
+        }
+    };
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries
+    {
+        let name: String = entry.file_name().to_string_lossy().into_owned();
+        let entry_path: String = if relative_path.is_empty() { name.clone() } else { format!("{}/{}", relative_path, name) };
+        if matches_any(&options.exclude, &name, &entry_path) { continue; }
+
+        // Symbolic links to directories are not followed, so a link cannot send the walk around in circles
+        let path = entry.path();
+        let is_directory: bool = entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false);
+        if is_directory
+        {
+            if options.recursive { walk_directory(&path, &entry_path, options, input, files, failures); }
+            continue;
+        }
+        if !path.is_file() || !wanted(&name, input) { continue; }
+        if !options.include.is_empty() && !matches_any(&options.include, &name, &entry_path) { continue; }
+        files.push(path.to_string_lossy().into_owned());
+    }
+}
+
+pub fn collect_files(paths: &[String], options: &BatchOptions, input: BatchInput) -> (Vec<String>, Vec<String>)
+{
+    // Returns the files to process and the paths that already failed, whose errors have been reported
+    let mut files: Vec<String> = Vec::new();
+    let mut failures: Vec<String> = Vec::new();
+    for path in paths
+    {
+        if !Path::new(path).is_dir() { files.push(path.clone()); }
+        else if options.recursive { walk_directory(Path::new(path), "", options, input, &mut files, &mut failures); }
+        else
+        {
+            eprintln!("{} is a directory; use --recursive to process the files in it", path);
+            failures.push(path.clone());
+        }
+    }
+    return (files, failures);
+}
+
+pub struct BatchSummary
+{
+    pub files: usize,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub failures: Vec<String>,
+}
+
+impl BatchSummary
+{
+    pub fn print(&self)
+    {
+        let succeeded: usize = self.files - self.failures.len();
+        println!("{} file(s) processed: {} succeeded, {} failed.", self.files, succeeded, self.failures.len());
+        if self.bytes_in > 0
+        {
+            println!("Bytes in: {}, bytes out: {} ({:.1}%).", self.bytes_in, self.bytes_out, self.bytes_out as f64 * 100.0 / self.bytes_in as f64);
+        }
+        else { println!("Bytes in: {}, bytes out: {}.", self.bytes_in, self.bytes_out); }
+        for failure in &self.failures { println!("Failed: {}", failure); }
+    }
+}
+
+pub fn run_batch(paths: &[String], options: &BatchOptions, input: BatchInput, output_path: Option<&str>, mut process: impl FnMut(&str) -> std::io::Result<(usize, usize)>) -> std::io::Result<()>
+{
+    // Every file is processed even after a failure; process and run_batch report their own errors
+    if paths.len() > 1 && paths.iter().any(|path| path == STDIO_PATH)
+    {
+        let e: Error = Error::new(ErrorKind::InvalidInput, "- (standard input) cannot be combined with other files");
+        eprintln!("{}", e);
+        return Err(e);
+    }
+    let (files, failures) = collect_files(paths, options, input);
+    if output_path.is_some() && files.len() + failures.len() > 1
+    {
+        let e: Error = Error::new(ErrorKind::InvalidInput, "--output names a single file, so it cannot be used with several input files");
+        eprintln!("{}", e);
+        return Err(e);
+    }
+
+    let mut summary: BatchSummary = BatchSummary { files: failures.len(), bytes_in: 0, bytes_out: 0, failures };
+    for file in &files
+    {
+        summary.files += 1;
+        match process(file)
+        {
+            Ok((bytes_in, bytes_out)) =>
+            {
+                summary.bytes_in += bytes_in as u64;
+                summary.bytes_out += bytes_out as u64;
+            }
+            Err(_) => { summary.failures.push(file.clone()); }
+        }
+    }
+
+    // A single file keeps the plain output of a single run
+    if paths.len() > 1 || paths.iter().any(|path| Path::new(path).is_dir()) { summary.print(); }
+    if !summary.failures.is_empty()
+    {
+        return Err(Error::other(format!("{} of {} file(s) failed", summary.failures.len(), summary.files)));
+    }
+    return Ok(());
+}
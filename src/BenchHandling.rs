@@ -0,0 +1,112 @@
+use std::fs;
+use std::time::Duration;
+
+fn status_kib(status: &str, field: &str) -> Option<usize>
+{
+    // Lines such as "VmHWM:     1234 kB"
+    let line: &str = status.lines().find(|line| line.starts_with(field))?;
+    return line[field.len()..].trim().trim_end_matches("kB").trim().parse().ok();
+}
+
+// Highest resident memory since start, on top of what was resident then; None where the kernel does not report it (outside Linux)
+pub struct PeakMemory
+{
+    baseline: Option<usize>,
+}
+
+impl PeakMemory
+{
+    pub fn start() -> Self
+    {
+        // Writing 5 to clear_refs resets the high-water mark to the current resident size, so the peak belongs to what runs next
+        let reset: bool = fs::write("/proc/self/clear_refs", "5").is_ok();
+        let baseline: Option<usize> = fs::read_to_string("/proc/self/status").ok()
+            .filter(|_| reset)
+            .and_then(|status| status_kib(&status, "VmRSS:"));
+        return PeakMemory { baseline };
+    }
+
+    pub fn peak(&self) -> Option<usize>
+    {
+        let baseline: usize = self.baseline?;
+        let peak: usize = status_kib(&fs::read_to_string("/proc/self/status").ok()?, "VmHWM:")?;
+        return Some(peak.saturating_sub(baseline) * 1024);
+    }
+}
+
+pub enum BenchOutcome
+{
+    // Best times over the runs, and the highest peak if it was measured
+    Measured { encoded_len: usize, encode_time: Duration, decode_time: Duration, peak_memory: Option<usize> },
+    Unavailable(String),
+    Failed(String),
+}
+
+pub struct BenchRow
+{
+    pub file: String,
+    pub pipeline: String,
+    pub original_len: usize,
+    pub outcome: BenchOutcome,
+}
+
+fn format_bytes(bytes: usize) -> String
+{
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value: f64 = bytes as f64;
+    let mut unit: usize = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1
+    {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { return format!("{} B", bytes); }
+    return format!("{:.1} {}", value, UNITS[unit]);
+}
+
+fn format_throughput(len: usize, time: Duration) -> String
+{
+    // Measured on the original size in both directions, so encode and decode compare directly
+    let seconds: f64 = time.as_secs_f64().max(1e-9);
+    return format!("{:.1} MiB/s", len as f64 / seconds / (1024.0 * 1024.0));
+}
+
+pub fn print_table(rows: &[BenchRow])
+{
+    let headers: [&str; 6] = ["File", "Pipeline", "Ratio", "Encode", "Decode", "Peak memory"];
+    let cells: Vec<[String; 6]> = rows.iter().map(|row|
+    {
+        let (ratio, encode, decode, memory) = match &row.outcome
+        {
+            BenchOutcome::Measured { encoded_len, encode_time, decode_time, peak_memory } =>
+            {
+                let ratio: String = if row.original_len == 0 { String::from("-") } else { format!("{:.1}%", *encoded_len as f64 * 100.0 / row.original_len as f64) };
+                (ratio, format_throughput(row.original_len, *encode_time), format_throughput(row.original_len, *decode_time), peak_memory.map(format_bytes).unwrap_or_else(|| String::from("-")))
+            }
+            BenchOutcome::Unavailable(reason) => (format!("unavailable: {}", reason), String::new(), String::new(), String::new()),
+            BenchOutcome::Failed(reason) => (format!("failed: {}", reason), String::new(), String::new(), String::new()),
+        };
+        return [row.file.clone(), row.pipeline.clone(), ratio, encode, decode, memory];
+    }).collect();
+
+    // Explanations of unavailable or failed pipelines run past the other columns
+    let mut widths: [usize; 6] = headers.map(str::len);
+    for row in &cells
+    {
+        for column in [0, 1, 3, 4, 5] { widths[column] = widths[column].max(row[column].chars().count()); }
+        if !row[3].is_empty() { widths[2] = widths[2].max(row[2].chars().count()); }
+    }
+
+    println!("{:<w0$}  {:<w1$}  {:>w2$}  {:>w3$}  {:>w4$}  {:>w5$}", headers[0], headers[1], headers[2], headers[3], headers[4], headers[5],
+        w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3], w4 = widths[4], w5 = widths[5]);
+    for row in &cells
+    {
+        if row[3].is_empty()
+        {
+            println!("{:<w0$}  {:<w1$}  {}", row[0], row[1], row[2], w0 = widths[0], w1 = widths[1]);
+            continue;
+        }
+        println!("{:<w0$}  {:<w1$}  {:>w2$}  {:>w3$}  {:>w4$}  {:>w5$}", row[0], row[1], row[2], row[3], row[4], row[5],
+            w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3], w4 = widths[4], w5 = widths[5]);
+    }
+}
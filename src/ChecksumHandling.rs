@@ -0,0 +1,171 @@
+use std::io::{Error, ErrorKind};
+
+use num_enum::TryFromPrimitive;
+
+use crate::EnvHandling::to_hex;
+use crate::HeaderHandling::{HeaderField, FIELD_OPTIONAL};
+
+// Readers that do not know checksums can still decode, so the field is optional
+pub const FIELD_CHECKSUM: u8 = FIELD_OPTIONAL | 0x01;
+// Streamed files only name the algorithm in the header (u8); the checksum record itself trails the stream
+pub const FIELD_STREAMED_CHECKSUM: u8 = FIELD_OPTIONAL | 0x06;
+
+#[derive(Debug, Clone, Copy, PartialEq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum ChecksumAlgorithm
+{
+    CRC32,
+    XXHash64,
+    BLAKE3,
+}
+
+impl ChecksumAlgorithm
+{
+    pub fn from_name(name: &str) -> Option<Self>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "crc32" => { return Some(ChecksumAlgorithm::CRC32); }
+            "xxhash64" | "xxh64" => { return Some(ChecksumAlgorithm::XXHash64); }
+            "blake3" => { return Some(ChecksumAlgorithm::BLAKE3); }
+            _ => { return None; }
+        }
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            ChecksumAlgorithm::CRC32 => { return "CRC32"; }
+            ChecksumAlgorithm::XXHash64 => { return "xxHash64"; }
+            ChecksumAlgorithm::BLAKE3 => { return "BLAKE3"; }
+        }
+    }
+}
+
+enum ChecksumState
+{
+    CRC32(crc32fast::Hasher),
+    XXHash64(Box<xxhash_rust::xxh64::Xxh64>),
+    BLAKE3(Box<blake3::Hasher>),
+}
+
+// Checksum of content fed piece by piece, so streamed content never has to be held in memory at once
+pub struct StreamingChecksum
+{
+    algorithm: ChecksumAlgorithm,
+    state: ChecksumState,
+    len: u64,
+}
+
+impl StreamingChecksum
+{
+    pub fn new(algorithm: ChecksumAlgorithm) -> Self
+    {
+        let state: ChecksumState = match algorithm
+        {
+            ChecksumAlgorithm::CRC32 => ChecksumState::CRC32(crc32fast::Hasher::new()),
+            ChecksumAlgorithm::XXHash64 => ChecksumState::XXHash64(Box::new(xxhash_rust::xxh64::Xxh64::new(0))),
+            ChecksumAlgorithm::BLAKE3 => ChecksumState::BLAKE3(Box::new(blake3::Hasher::new())),
+        };
+        return StreamingChecksum { algorithm, state, len: 0 };
+    }
+
+    pub fn update(&mut self, data: &[u8])
+    {
+        match &mut self.state
+        {
+            ChecksumState::CRC32(hasher) => { hasher.update(data); }
+            ChecksumState::XXHash64(hasher) => { hasher.update(data); }
+            ChecksumState::BLAKE3(hasher) => { hasher.update(data); }
+        }
+        self.len += data.len() as u64;
+    }
+
+    pub fn field(self) -> HeaderField
+    {
+        // Value: algorithm (u8), original length (u64), digest of the original content
+        let digest: Vec<u8> = match self.state
+        {
+            ChecksumState::CRC32(hasher) => hasher.finalize().to_be_bytes().to_vec(),
+            ChecksumState::XXHash64(hasher) => hasher.digest().to_be_bytes().to_vec(),
+            ChecksumState::BLAKE3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        };
+        let mut value: Vec<u8> = Vec::with_capacity(9 + digest.len());
+        value.push(self.algorithm as u8);
+        value.extend_from_slice(&self.len.to_be_bytes());
+        value.extend_from_slice(&digest);
+        return HeaderField { kind: FIELD_CHECKSUM, value };
+    }
+}
+
+pub fn checksum_field(algorithm: ChecksumAlgorithm, data: &[u8]) -> HeaderField
+{
+    let mut checksum: StreamingChecksum = StreamingChecksum::new(algorithm);
+    checksum.update(data);
+    return checksum.field();
+}
+
+fn parse_checksum_field(field: &HeaderField) -> std::io::Result<(ChecksumAlgorithm, u64, &[u8])>
+{
+    let malformed = || Error::new(ErrorKind::InvalidData, "Checksum field is malformed");
+    let algorithm: ChecksumAlgorithm = ChecksumAlgorithm::try_from(*field.value.first().ok_or_else(malformed)?)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, format!("Unknown checksum algorithm: {}", field.value[0])))?;
+    let original_len: u64 = u64::from_be_bytes(field.value.get(1..9).ok_or_else(malformed)?.try_into().expect("Length takes 8 bytes"));
+    return Ok((algorithm, original_len, &field.value[9..]));
+}
+
+pub fn stored_checksum(fields: &[HeaderField]) -> std::io::Result<Option<(ChecksumAlgorithm, u64)>>
+{
+    // Algorithm and original length, for inspecting a file without decoding it
+    match fields.iter().find(|field| field.kind == FIELD_CHECKSUM)
+    {
+        Some(field) => { let (algorithm, original_len, _) = parse_checksum_field(field)?; return Ok(Some((algorithm, original_len))); }
+        None => { return Ok(None); }
+    }
+}
+
+pub fn streamed_checksum(fields: &[HeaderField]) -> std::io::Result<Option<ChecksumAlgorithm>>
+{
+    // Algorithm a streamed file announces in its header, so its content can be hashed while it is decoded
+    match fields.iter().find(|field| field.kind == FIELD_STREAMED_CHECKSUM)
+    {
+        Some(field) if field.value.len() == 1 =>
+        {
+            return ChecksumAlgorithm::try_from(field.value[0]).map(Some)
+                .map_err(|_| Error::new(ErrorKind::InvalidData, format!("Unknown checksum algorithm: {}", field.value[0])));
+        }
+        Some(_) => { return Err(Error::new(ErrorKind::InvalidData, "Streamed checksum field is malformed")); }
+        None => { return Ok(None); }
+    }
+}
+
+fn compare_checksum(stored: &HeaderField, computed: &HeaderField) -> std::io::Result<ChecksumAlgorithm>
+{
+    let (algorithm, original_len, stored_digest) = parse_checksum_field(stored)?;
+    let (_, decoded_len, digest) = parse_checksum_field(computed)?;
+    if decoded_len != original_len
+    {
+        return Err(Error::new(ErrorKind::InvalidData, format!("Decoded content is {} bytes long, but the original was {} bytes", decoded_len, original_len)));
+    }
+    if digest != stored_digest
+    {
+        return Err(Error::new(ErrorKind::InvalidData, format!("Decoded content does not match the stored {} checksum ({} expected, {} found)",
+            algorithm.name(), to_hex(stored_digest), to_hex(digest))));
+    }
+    return Ok(algorithm);
+}
+
+pub fn verify_checksum(fields: &[HeaderField], data: &[u8]) -> std::io::Result<Option<ChecksumAlgorithm>>
+{
+    // Files written without a checksum have nothing to verify
+    let Some(field) = fields.iter().find(|field| field.kind == FIELD_CHECKSUM) else { return Ok(None); };
+    let (algorithm, _, _) = parse_checksum_field(field)?;
+    return compare_checksum(field, &checksum_field(algorithm, data)).map(Some);
+}
+
+pub fn verify_streamed_checksum(record: &[u8], checksum: StreamingChecksum) -> std::io::Result<ChecksumAlgorithm>
+{
+    // The record trailing a stream is the value of a regular checksum field
+    return compare_checksum(&HeaderField { kind: FIELD_CHECKSUM, value: record.to_vec() }, &checksum.field());
+}
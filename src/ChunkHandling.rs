@@ -0,0 +1,378 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+
+use zeroize::Zeroizing;
+
+use crate::Codec::StageParams;
+
+// Seek table appended to chunked payloads: one entry per chunk, then the table length (u64), the chunk count (u64) and this magic
+const SEEK_MAGIC: &[u8; 8] = b"RSZSEEK1";
+const FOOTER_LEN: usize = 8 + 8 + 8;
+// Entry: encoded length (u64), original length (u64), CRC32 of the original chunk (u32), then the stage count (u8)
+// and every stage's parameter record for this chunk (u16 length + bytes)
+const ENTRY_LEN: usize = 8 + 8 + 4 + 1;
+
+// Streamed payloads have no seek table: every chunk follows its own entry, and an entry with no encoded bytes ends the stream
+// The end entry holds the total original length and is followed by the checksum record (u16 length + value, empty when none)
+const STREAM_END_LEN: u64 = 0;
+
+// Encoded chunk with the parameters every stage recorded for it
+pub type EncodedChunk = (Vec<u8>, Vec<StageParams>);
+
+pub struct ChunkEntry
+{
+    pub offset: u64,
+    pub encoded_len: u64,
+    pub original_offset: u64,
+    pub original_len: u64,
+    pub crc32: u32,
+    pub params: Vec<StageParams>,
+}
+
+pub fn parse_byte_size(arg: &str) -> Option<usize>
+{
+    // Chunk and volume sizes: plain bytes, or with a K, M or G suffix (powers of 1024)
+    let (digits, multiplier) = match arg.char_indices().last()?
+    {
+        (i, 'k' | 'K') => (&arg[..i], 1usize << 10),
+        (i, 'm' | 'M') => (&arg[..i], 1usize << 20),
+        (i, 'g' | 'G') => (&arg[..i], 1usize << 30),
+        _ => (arg, 1),
+    };
+    return digits.parse::<usize>().ok()?.checked_mul(multiplier).filter(|&size| size > 0);
+}
+
+fn write_entry(output: &mut impl Write, encoded_len: u64, original_len: u64, crc32: u32, params: &[StageParams]) -> std::io::Result<()>
+{
+    output.write_all(&encoded_len.to_be_bytes())?;
+    output.write_all(&original_len.to_be_bytes())?;
+    output.write_all(&crc32.to_be_bytes())?;
+    output.write_all(&[params.len() as u8])?;
+    for stage_params in params
+    {
+        output.write_all(&(stage_params.len() as u16).to_be_bytes())?;
+        output.write_all(stage_params)?;
+    }
+    return Ok(());
+}
+
+fn read_entry(input: &mut impl Read) -> std::io::Result<ChunkEntry>
+{
+    // Offsets are left at zero for the caller, which knows where the entry sits
+    let mut raw_entry: [u8; ENTRY_LEN] = [0u8; ENTRY_LEN];
+    input.read_exact(&mut raw_entry)?;
+    let encoded_len: u64 = u64::from_be_bytes(raw_entry[..8].try_into().expect("Encoded length takes 8 bytes"));
+    let original_len: u64 = u64::from_be_bytes(raw_entry[8..16].try_into().expect("Original length takes 8 bytes"));
+    let crc32: u32 = u32::from_be_bytes(raw_entry[16..20].try_into().expect("CRC32 takes 4 bytes"));
+
+    let mut params: Vec<StageParams> = Vec::with_capacity(raw_entry[20] as usize);
+    for _ in 0..raw_entry[20]
+    {
+        let mut params_len: [u8; 2] = [0u8; 2];
+        input.read_exact(&mut params_len)?;
+        let mut stage_params: StageParams = vec![0u8; u16::from_be_bytes(params_len) as usize];
+        input.read_exact(&mut stage_params)?;
+        params.push(stage_params);
+    }
+    return Ok(ChunkEntry { offset: 0, encoded_len, original_offset: 0, original_len, crc32, params });
+}
+
+fn write_seek_table(payload: &mut Vec<u8>, table: &[u8], chunks_count: u64)
+{
+    payload.extend_from_slice(table);
+    payload.extend_from_slice(&(table.len() as u64).to_be_bytes());
+    payload.extend_from_slice(&chunks_count.to_be_bytes());
+    payload.extend_from_slice(SEEK_MAGIC);
+}
+
+pub fn frame_chunks(data: &[u8], chunk_size: usize, mut encode_chunk: impl FnMut(&[u8]) -> std::io::Result<EncodedChunk>) -> std::io::Result<Vec<u8>>
+{
+    // Every chunk runs through the whole pipeline on its own, so any of them can be decoded alone
+    let mut payload: Vec<u8> = Vec::new();
+    let mut table: Vec<u8> = Vec::new();
+    let mut chunks_count: u64 = 0;
+    for chunk in data.chunks(chunk_size)
+    {
+        let (encoded, params) = encode_chunk(chunk)?;
+        write_entry(&mut table, encoded.len() as u64, chunk.len() as u64, crc32fast::hash(chunk), &params)?;
+        payload.extend_from_slice(&encoded);
+        chunks_count += 1;
+    }
+
+    write_seek_table(&mut payload, &table, chunks_count);
+    return Ok(payload);
+}
+
+fn parse_entries(table: &[u8], chunks_count: usize, chunks_len: u64) -> std::io::Result<Vec<ChunkEntry>>
+{
+    // Lengths are summed with checked_add and must end exactly at the seek table, so every entry lies within the chunk area
+    let mismatch = || Error::new(ErrorKind::InvalidData, "Seek table does not match the chunks it indexes");
+    let mut input: &[u8] = table;
+    let mut entries: Vec<ChunkEntry> = Vec::with_capacity(chunks_count);
+    let (mut offset, mut original_offset): (u64, u64) = (0, 0);
+    for _ in 0..chunks_count
+    {
+        let mut entry: ChunkEntry = read_entry(&mut input).map_err(|_| mismatch())?;
+        entry.offset = offset;
+        entry.original_offset = original_offset;
+        offset = offset.checked_add(entry.encoded_len).ok_or_else(mismatch)?;
+        original_offset = original_offset.checked_add(entry.original_len).ok_or_else(mismatch)?;
+        entries.push(entry);
+    }
+    if !input.is_empty() || offset != chunks_len { return Err(mismatch()); }
+    return Ok(entries);
+}
+
+fn table_len(footer: &[u8], payload_len: u64) -> std::io::Result<(usize, u64)>
+{
+    // Returns chunk count and seek table length, checking that the table fits in the payload and can hold that many entries
+    if footer.len() != FOOTER_LEN || &footer[16..] != SEEK_MAGIC { return Err(Error::new(ErrorKind::InvalidData, "Chunked payload has no seek table")); }
+    let table_len: u64 = u64::from_be_bytes(footer[..8].try_into().expect("Table length takes 8 bytes"));
+    let chunks_count: u64 = u64::from_be_bytes(footer[8..16].try_into().expect("Chunk count takes 8 bytes"));
+    if table_len > payload_len - FOOTER_LEN as u64 || chunks_count > table_len / ENTRY_LEN as u64
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Seek table is larger than the payload"));
+    }
+    return Ok((chunks_count as usize, table_len));
+}
+
+pub fn seek_table(payload: &[u8]) -> std::io::Result<(Vec<ChunkEntry>, usize)>
+{
+    // Returns the chunk entries and the length of the chunk area that precedes the table
+    if payload.len() < FOOTER_LEN { return Err(Error::new(ErrorKind::InvalidData, "Chunked payload has no seek table")); }
+    let (chunks_count, table_len) = table_len(&payload[payload.len() - FOOTER_LEN..], payload.len() as u64)?;
+    let chunks_len: usize = payload.len() - FOOTER_LEN - table_len as usize;
+    let entries: Vec<ChunkEntry> = parse_entries(&payload[chunks_len..chunks_len + table_len as usize], chunks_count, chunks_len as u64)?;
+    return Ok((entries, chunks_len));
+}
+
+fn check_chunk(original_len: u64, crc32: u32, decoded: &[u8], index: usize) -> std::io::Result<()>
+{
+    if decoded.len() as u64 != original_len || crc32fast::hash(decoded) != crc32
+    {
+        return Err(Error::new(ErrorKind::InvalidData, format!("Chunk {} does not match its stored length and CRC32: it is corrupted", index)));
+    }
+    return Ok(());
+}
+
+pub fn decode_chunks(payload: &[u8], mut decode_chunk: impl FnMut(&[u8], &[StageParams]) -> std::io::Result<Vec<u8>>) -> std::io::Result<Vec<u8>>
+{
+    let (entries, _) = seek_table(payload)?;
+    // Decoded chunks are plaintext, so they are wiped when a later chunk fails as well
+    let mut decoded_data: Zeroizing<Vec<u8>> = Zeroizing::new(Vec::new());
+    for (index, entry) in entries.iter().enumerate()
+    {
+        let decoded: Zeroizing<Vec<u8>> = Zeroizing::new(decode_chunk(&payload[entry.offset as usize..(entry.offset + entry.encoded_len) as usize], &entry.params)?);
+        check_chunk(entry.original_len, entry.crc32, &decoded, index)?;
+        decoded_data.extend_from_slice(&decoded);
+    }
+    return Ok(std::mem::take(&mut *decoded_data));
+}
+
+pub fn map_chunks(payload: &[u8], mut recode_chunk: impl FnMut(&[u8], &[StageParams]) -> std::io::Result<EncodedChunk>) -> std::io::Result<Vec<u8>>
+{
+    // Re-encodes every chunk in place; original lengths and checksums do not change, but parameters may
+    let (entries, _) = seek_table(payload)?;
+    let mut recoded_payload: Vec<u8> = Vec::with_capacity(payload.len());
+    let mut table: Vec<u8> = Vec::new();
+    for entry in &entries
+    {
+        let (recoded, params) = recode_chunk(&payload[entry.offset as usize..(entry.offset + entry.encoded_len) as usize], &entry.params)?;
+        write_entry(&mut table, recoded.len() as u64, entry.original_len, entry.crc32, &params)?;
+        recoded_payload.extend_from_slice(&recoded);
+    }
+
+    write_seek_table(&mut recoded_payload, &table, entries.len() as u64);
+    return Ok(recoded_payload);
+}
+
+pub fn read_range(file: &mut File, payload_start: u64, payload_end: u64, start: u64, end: Option<u64>,
+    mut decode_chunk: impl FnMut(&[u8], &[StageParams]) -> std::io::Result<Vec<u8>>) -> std::io::Result<Vec<u8>>
+{
+    // Only the seek table and the chunks overlapping [start, end) are read from disk
+    let payload_len: u64 = payload_end.checked_sub(payload_start).filter(|&len| len >= FOOTER_LEN as u64)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Chunked payload has no seek table"))?;
+    let mut footer: [u8; FOOTER_LEN] = [0u8; FOOTER_LEN];
+    file.seek(SeekFrom::Start(payload_end - FOOTER_LEN as u64))?;
+    file.read_exact(&mut footer)?;
+    let (chunks_count, table_len) = table_len(&footer, payload_len)?;
+
+    let mut table: Vec<u8> = vec![0u8; table_len as usize];
+    file.seek(SeekFrom::Start(payload_end - FOOTER_LEN as u64 - table_len))?;
+    file.read_exact(&mut table)?;
+    let entries: Vec<ChunkEntry> = parse_entries(&table, chunks_count, payload_len - FOOTER_LEN as u64 - table_len)?;
+
+    // parse_entries has checked that every original offset and length add up without overflowing
+    let total_len: u64 = entries.last().map(|entry| entry.original_offset + entry.original_len).unwrap_or(0);
+    let end: u64 = end.unwrap_or(total_len).min(total_len);
+    if start > end { return Err(Error::new(ErrorKind::InvalidInput, format!("Range starts after its end or past the content ({} bytes)", total_len))); }
+
+    // Grown as chunks are decoded: the lengths in the table are only trusted once a chunk matches them
+    let mut range: Zeroizing<Vec<u8>> = Zeroizing::new(Vec::new());
+    for (index, entry) in entries.iter().enumerate()
+    {
+        if entry.original_offset + entry.original_len <= start || entry.original_offset >= end { continue; }
+
+        let mut encoded: Vec<u8> = vec![0u8; entry.encoded_len as usize];
+        file.seek(SeekFrom::Start(payload_start + entry.offset))?;
+        file.read_exact(&mut encoded)?;
+        let decoded: Zeroizing<Vec<u8>> = Zeroizing::new(decode_chunk(&encoded, &entry.params)?);
+        check_chunk(entry.original_len, entry.crc32, &decoded, index)?;
+
+        let from: usize = start.saturating_sub(entry.original_offset) as usize;
+        let to: usize = (end - entry.original_offset).min(entry.original_len) as usize;
+        range.extend_from_slice(&decoded[from..to]);
+    }
+    return Ok(std::mem::take(&mut *range));
+}
+
+pub enum StreamFrame
+{
+    Chunk { original_len: u64, crc32: u32, params: Vec<StageParams>, encoded: Vec<u8> },
+    End { original_len: u64, checksum: Vec<u8> },
+}
+
+pub fn write_frame(output: &mut impl Write, chunk: &[u8], encoded: &[u8], params: &[StageParams]) -> std::io::Result<()>
+{
+    if encoded.is_empty() { return Err(Error::new(ErrorKind::InvalidData, "Chunk encoded to nothing, which would end the stream")); }
+    write_entry(output, encoded.len() as u64, chunk.len() as u64, crc32fast::hash(chunk), params)?;
+    return output.write_all(encoded);
+}
+
+pub fn write_stream_end(output: &mut impl Write, original_len: u64, checksum: &[u8]) -> std::io::Result<()>
+{
+    write_entry(output, STREAM_END_LEN, original_len, 0, &[])?;
+    output.write_all(&(checksum.len() as u16).to_be_bytes())?;
+    return output.write_all(checksum);
+}
+
+pub fn read_frame(input: &mut impl Read) -> std::io::Result<StreamFrame>
+{
+    let truncated = |e: Error| if e.kind() == ErrorKind::UnexpectedEof { Error::new(ErrorKind::UnexpectedEof, "Stream is truncated: it ends before its end frame") } else { e };
+    let ChunkEntry { encoded_len, original_len, crc32, params, .. } = read_entry(input).map_err(truncated)?;
+
+    if encoded_len == STREAM_END_LEN
+    {
+        let mut checksum_len: [u8; 2] = [0u8; 2];
+        input.read_exact(&mut checksum_len).map_err(truncated)?;
+        let mut checksum: Vec<u8> = vec![0u8; u16::from_be_bytes(checksum_len) as usize];
+        input.read_exact(&mut checksum).map_err(truncated)?;
+        return Ok(StreamFrame::End { original_len, checksum });
+    }
+
+    // Lengths come from the stream itself, so the chunk is read through take() rather than allocated up front
+    let mut encoded: Vec<u8> = Vec::new();
+    input.take(encoded_len).read_to_end(&mut encoded)?;
+    if (encoded.len() as u64) < encoded_len { return Err(truncated(Error::from(ErrorKind::UnexpectedEof))); }
+    return Ok(StreamFrame::Chunk { original_len, crc32, params, encoded });
+}
+
+pub fn read_stream(input: &mut impl Read, mut decode_chunk: impl FnMut(&[u8], &[StageParams]) -> std::io::Result<Vec<u8>>,
+    mut write_chunk: impl FnMut(&[u8]) -> std::io::Result<()>) -> std::io::Result<Vec<u8>>
+{
+    // Chunks are checked and handed over one at a time; the checksum record at the end is returned for the caller to verify
+    let mut decoded_len: u64 = 0;
+    let mut index: usize = 0;
+    loop
+    {
+        match read_frame(input)?
+        {
+            StreamFrame::Chunk { original_len, crc32, params, encoded } =>
+            {
+                let decoded: Zeroizing<Vec<u8>> = Zeroizing::new(decode_chunk(&encoded, &params)?);
+                check_chunk(original_len, crc32, &decoded, index)?;
+                decoded_len += original_len;
+                index += 1;
+                write_chunk(&decoded)?;
+            }
+            StreamFrame::End { original_len, checksum } =>
+            {
+                if original_len != decoded_len
+                {
+                    return Err(Error::new(ErrorKind::InvalidData, format!("Stream ends after {} bytes, but its end frame declares {}", decoded_len, original_len)));
+                }
+                return Ok(checksum);
+            }
+        }
+    }
+}
+
+pub fn map_frames(payload: &[u8], mut recode_chunk: impl FnMut(&[u8], &[StageParams]) -> std::io::Result<EncodedChunk>) -> std::io::Result<Vec<u8>>
+{
+    // Streamed counterpart of map_chunks: every frame is rewritten with its new encoded length and parameters
+    let mut input: &[u8] = payload;
+    let mut recoded_payload: Vec<u8> = Vec::with_capacity(payload.len());
+    loop
+    {
+        match read_frame(&mut input)?
+        {
+            StreamFrame::Chunk { original_len, crc32, params, encoded } =>
+            {
+                let (recoded, params) = recode_chunk(&encoded, &params)?;
+                if recoded.is_empty() { return Err(Error::new(ErrorKind::InvalidData, "Chunk encoded to nothing, which would end the stream")); }
+                write_entry(&mut recoded_payload, recoded.len() as u64, original_len, crc32, &params)?;
+                recoded_payload.extend_from_slice(&recoded);
+            }
+            StreamFrame::End { original_len, checksum } =>
+            {
+                write_stream_end(&mut recoded_payload, original_len, &checksum)?;
+                recoded_payload.extend_from_slice(input);
+                return Ok(recoded_payload);
+            }
+        }
+    }
+}
+
+pub fn stream_frames(payload: &[u8]) -> std::io::Result<(usize, u64, usize)>
+{
+    // Chunk count, original length and the length taken by the stream, walking the frames without decoding them
+    let mut input: &[u8] = payload;
+    let mut chunks_count: usize = 0;
+    loop
+    {
+        match read_frame(&mut input)?
+        {
+            StreamFrame::Chunk { .. } => { chunks_count += 1; }
+            StreamFrame::End { original_len, .. } => { return Ok((chunks_count, original_len, payload.len() - input.len())); }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn chunks_keep_their_own_params()
+    {
+        // Every chunk records a different parameter, as a per-chunk nonce would
+        let data: Vec<u8> = (0..100u8).collect();
+        let mut nonce: u8 = 0;
+        let payload: Vec<u8> = frame_chunks(&data, 30, |chunk| { nonce += 1; return Ok((chunk.to_vec(), vec![vec![nonce]])); }).unwrap();
+
+        let mut seen: Vec<StageParams> = Vec::new();
+        let decoded: Vec<u8> = decode_chunks(&payload, |chunk, params| { seen.push(params[0].clone()); return Ok(chunk.to_vec()); }).unwrap();
+        assert_eq!(decoded, data);
+        assert_eq!(seen, vec![vec![1], vec![2], vec![3], vec![4]]);
+    }
+
+    #[test]
+    fn overflowing_seek_table_is_rejected()
+    {
+        // Two entries whose original lengths overflow u64 when added, pointing at an empty chunk area
+        let mut table: Vec<u8> = Vec::new();
+        write_entry(&mut table, 0, u64::MAX, 0, &[]).unwrap();
+        write_entry(&mut table, 0, 2, 0, &[]).unwrap();
+        let mut payload: Vec<u8> = Vec::new();
+        write_seek_table(&mut payload, &table, 2);
+        assert!(seek_table(&payload).is_err());
+
+        // An entry reaching past the chunk area
+        let mut table: Vec<u8> = Vec::new();
+        write_entry(&mut table, 1 << 40, 1, 0, &[]).unwrap();
+        let mut payload: Vec<u8> = vec![0u8; 4];
+        write_seek_table(&mut payload, &table, 1);
+        assert!(seek_table(&payload).is_err());
+    }
+}
@@ -0,0 +1,653 @@
+use clap::{ArgAction, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap::error::ErrorKind as ClapErrorKind;
+use ed25519_dalek::VerifyingKey;
+use glob::Pattern;
+use zeroize::Zeroizing;
+
+use crate::Codec::CodecList;
+use crate::ChecksumHandling::ChecksumAlgorithm;
+use crate::ChunkHandling::parse_byte_size;
+use crate::BatchHandling::BatchOptions;
+use crate::EnvHandling::{HeaderOptions, OutputOptions, STDIO_PATH};
+use crate::KeyHandling::resolve_key;
+use crate::SignatureHandling::load_verifying_key;
+use crate::RZ_KEY_TYPE;
+
+const KEY_SOURCES: &str = "Keys may be given literally or as file:<path>, env:<VAR>, fd:<n> or prompt (pass:<key> for literal keys with those prefixes)";
+
+#[derive(Parser)]
+#[command(name = "RustyZipper", version, about = "Compresses, encrypts and signs files through a pipeline of codecs", after_help = KEY_SOURCES)]
+pub struct Cli
+{
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command
+{
+    /// Encode files through the given codecs, in the order given, into <file>.rsz
+    Compress(CompressArgs),
+    /// Decode .rsz files (or any of their volumes) back into the original files
+    Decompress(DecompressArgs),
+    /// Decode .rsz files in memory and check their checksum and length, without writing anything
+    Test(TestArgs),
+    /// Show how an .rsz file was built, without keys and without decoding it
+    Info(FileArgs),
+    /// Compare codec pipelines on files: ratio, encode and decode throughput, and peak memory
+    Bench(BenchArgs),
+    /// Rebuild damaged parts of an .rsz file from its recovery record
+    Repair(FileArgs),
+    /// Decode a byte range of a chunked .rsz file to standard output
+    Range(RangeArgs),
+    /// Generate an X25519 (encryption) or Ed25519 (signing) keypair: <name>.pub and <name>.key
+    Keygen(KeygenArgs),
+    /// Sign an .rsz file, embedding the signature or writing it to <file>.sig
+    Sign(SignArgs),
+    /// Check the signature of an .rsz file against a trusted public key
+    Verify(VerifyArgs),
+    /// Change the keys of the encrypted stages without decoding the rest of the pipeline
+    Rekey(RekeyArgs),
+    /// Recover the shift of a Caesar stage by frequency analysis (training only)
+    Attack(AttackArgs),
+    /// Add a key slot, unlocking the data key with an existing credential
+    AddSlot(AddSlotArgs),
+    /// Remove a key slot, unlocking the data key with an existing credential
+    RemoveSlot(RemoveSlotArgs),
+    /// Split the data key of a key slot stage into Shamir shares
+    SplitKey(SplitKeyArgs),
+}
+
+#[derive(Args)]
+pub struct FileArgs
+{
+    /// Path to the file
+    pub file: String,
+}
+
+#[derive(Args)]
+#[command(after_help = KEY_SOURCES)]
+pub struct CompressArgs
+{
+    /// Files or directories to encode, or - to encode standard input to standard output as a stream
+    #[arg(required = true)]
+    pub files: Vec<String>,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    #[command(flatten)]
+    batch: BatchArgs,
+
+    #[command(flatten)]
+    codecs: CodecArgs,
+
+    /// Conceal the header (pipeline, key slots and metadata) with this key, as the outermost layer
+    #[arg(long, value_parser = parse_secret, value_name = "KEY", help_heading = "Container")]
+    pub conceal: Option<Zeroizing<String>>,
+    /// Pad the concealed container to hide its size
+    #[arg(long, requires = "conceal", help_heading = "Container")]
+    pub pad: bool,
+    /// Checksum of the original content: crc32, xxhash64, blake3 or none [default: crc32, or none for encrypted content in a header that is not concealed]
+    #[arg(long, value_name = "ALGORITHM", value_parser = parse_checksum, help_heading = "Container")]
+    pub checksum: Option<ChecksumChoice>,
+    /// Do not record the original name, permissions and modification time
+    #[arg(long, help_heading = "Container")]
+    pub no_metadata: bool,
+    /// Record extended attributes too
+    #[arg(long, conflicts_with = "no_metadata", help_heading = "Container")]
+    pub xattrs: bool,
+    /// Encode in chunks of this size (K, M or G suffix), with a seek table for range decoding
+    #[arg(long, value_name = "BYTES", value_parser = parse_size, help_heading = "Container")]
+    pub chunk_size: Option<usize>,
+    /// Add Reed-Solomon parity of this size, as a percentage from 1 to 100
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent, help_heading = "Container")]
+    pub recovery: Option<u8>,
+    /// Split the encoded file into numbered volumes of at most this size (K, M or G suffix)
+    #[arg(long, value_name = "BYTES", value_parser = parse_size, help_heading = "Container")]
+    pub volume_size: Option<usize>,
+
+    // Codec options in the order they were given, filled in from the raw matches
+    #[arg(skip)]
+    pipeline: Vec<(CodecList, Option<Zeroizing<String>>)>,
+}
+
+// Every codec option adds one stage; the value, when there is one, belongs to that stage only
+// The fields only declare the options: stages are read from the matches, which keep their order
+#[allow(dead_code)]
+#[derive(Args)]
+#[command(next_help_heading = "Codecs (applied in the order given, may be repeated)")]
+struct CodecArgs
+{
+    /// Huffman compression
+    #[arg(long, action = ArgAction::Append, num_args = 0, default_missing_value = "true")]
+    huffman: Vec<String>,
+    /// Run-length encoding
+    #[arg(long, action = ArgAction::Append, num_args = 0, default_missing_value = "true")]
+    rle: Vec<String>,
+    /// Caesar cipher with this key (training only)
+    #[arg(long, value_parser = parse_secret, value_name = "KEY")]
+    caesar: Vec<Zeroizing<String>>,
+    /// AES-128 with this key
+    #[arg(long, value_parser = parse_secret, value_name = "KEY")]
+    aes: Vec<Zeroizing<String>>,
+    /// Vigenère cipher with this key (training only)
+    #[arg(long, value_parser = parse_secret, value_name = "KEY")]
+    vigenere: Vec<Zeroizing<String>>,
+    /// XOR stream with this key (training only)
+    #[arg(long, value_parser = parse_secret, value_name = "KEY")]
+    xor: Vec<Zeroizing<String>>,
+    /// Encrypt to these X25519 public key files (comma-separated)
+    #[arg(long, value_name = "PUBLIC KEYS")]
+    recipient: Vec<String>,
+    /// Encrypt with a data key opened by any of these slots: pw:<password> or public key files (comma-separated)
+    #[arg(long, value_parser = parse_secret, value_name = "SLOTS")]
+    keyslots: Vec<Zeroizing<String>>,
+}
+
+const CODEC_OPTIONS: [(&str, CodecList); 8] =
+[
+    ("huffman", CodecList::Huffman),
+    ("rle", CodecList::RLE),
+    ("caesar", CodecList::Caesar),
+    ("aes", CodecList::AES),
+    ("vigenere", CodecList::Vigenere),
+    ("xor", CodecList::XOR),
+    ("recipient", CodecList::X25519),
+    ("keyslots", CodecList::KeySlots),
+];
+
+// Everything encode_file needs from the codec options, with keys already read
+pub struct EncodePlan
+{
+    pub codecs: Vec<u8>,
+    pub keys: Vec<RZ_KEY_TYPE>,
+    pub recipients: Vec<Vec<String>>,
+    pub key_slots: Vec<Zeroizing<String>>,
+    pub conceal: Option<RZ_KEY_TYPE>,
+}
+
+impl CompressArgs
+{
+    fn read_pipeline(&mut self, matches: &ArgMatches)
+    {
+        // Clap groups values by option, so stages are put back in command line order through their indices
+        let mut stages: Vec<(usize, CodecList, Option<Zeroizing<String>>)> = Vec::new();
+        for (id, codec) in CODEC_OPTIONS
+        {
+            let Some(indices) = matches.indices_of(id) else { continue; };
+            if matches!(codec, CodecList::Huffman | CodecList::RLE)
+            {
+                stages.extend(indices.map(|index| (index, codec, None)));
+            }
+            else if codec == CodecList::X25519
+            {
+                let values = matches.get_many::<String>(id).into_iter().flatten().map(|value| Zeroizing::new(value.clone()));
+                stages.extend(indices.zip(values).map(|(index, value)| (index, codec, Some(value))));
+            }
+            else
+            {
+                let values = matches.get_many::<Zeroizing<String>>(id).into_iter().flatten().cloned();
+                stages.extend(indices.zip(values).map(|(index, value)| (index, codec, Some(value))));
+            }
+        }
+        stages.sort_by_key(|(index, _, _)| *index);
+        self.pipeline = stages.into_iter().map(|(_, codec, value)| (codec, value)).collect();
+    }
+
+    pub fn is_stdin(&self) -> bool
+    {
+        return self.files.len() == 1 && self.files[0] == STDIO_PATH;
+    }
+
+    pub fn output_options(&self) -> OutputOptions
+    {
+        return self.output.options();
+    }
+
+    pub fn batch_options(&self) -> BatchOptions
+    {
+        return self.batch.options();
+    }
+
+    pub fn header_options(&self) -> HeaderOptions
+    {
+        // The checksum and length are stored in clear, where they would let anyone confirm guesses about encrypted content
+        let encrypted: bool = self.pipeline.iter().any(|(codec, _)| codec.needs_key() || matches!(codec, CodecList::X25519 | CodecList::KeySlots));
+        let checksum: Option<ChecksumAlgorithm> = match self.checksum
+        {
+            Some(ChecksumChoice(checksum)) => checksum,
+            None if encrypted && self.conceal.is_none() => None,
+            None => Some(ChecksumAlgorithm::CRC32),
+        };
+        return HeaderOptions
+        {
+            checksum,
+            metadata: !self.no_metadata,
+            xattrs: self.xattrs,
+            chunk_size: self.chunk_size,
+            recovery: self.recovery,
+            volume_size: self.volume_size,
+        };
+    }
+
+    pub fn plan(&self) -> std::io::Result<EncodePlan>
+    {
+        let mut plan: EncodePlan = EncodePlan { codecs: Vec::new(), keys: Vec::new(), recipients: Vec::new(), key_slots: Vec::new(), conceal: None };
+        for (codec, value) in &self.pipeline
+        {
+            let value: &str = value.as_deref().map(String::as_str).unwrap_or_default();
+            match codec
+            {
+                CodecList::X25519 => { plan.recipients.push(value.split(',').map(String::from).collect()); }
+                CodecList::KeySlots => { plan.key_slots = value.split(',').map(|spec| Zeroizing::new(String::from(spec))).collect(); }
+                codec if codec.needs_key() =>
+                {
+                    let label: String = format!("Key {}", plan.keys.len() + 1);
+                    plan.keys.push(resolve_key(value, &label, true).map_err(|e| std::io::Error::new(e.kind(), format!("{} could not be read: {}", label, e)))?);
+                }
+                _ => {}
+            }
+            plan.codecs.push(*codec as u8);
+        }
+        if let Some(conceal) = &self.conceal
+        {
+            plan.conceal = Some(resolve_key(conceal, "Concealing key", true).map_err(|e| std::io::Error::new(e.kind(), format!("Concealing key could not be read: {}", e)))?);
+        }
+        return Ok(plan);
+    }
+}
+
+// Everything decoding needs besides the files, with keys already read
+pub struct DecodePlan
+{
+    pub keys: Vec<RZ_KEY_TYPE>,
+    pub identities: Vec<String>,
+    pub shares: Vec<String>,
+    pub signer: Option<VerifyingKey>,
+}
+
+#[derive(Args)]
+pub struct DecodeKeyArgs
+{
+    /// Key of an encrypted stage, repeated in decoding order: the outermost stage (or the concealing key) first
+    #[arg(short, long = "key", value_parser = parse_secret, value_name = "KEY")]
+    pub keys: Vec<Zeroizing<String>>,
+    /// X25519 private key file for recipient and key slot stages
+    #[arg(long = "identity", value_name = "KEY FILE")]
+    pub identities: Vec<String>,
+    /// Key share file, instead of a key slot password
+    #[arg(long = "share", value_name = "SHARE FILE")]
+    pub shares: Vec<String>,
+    /// Only decode files carrying an embedded signature by this trusted Ed25519 public key file
+    #[arg(long, value_name = "PUBLIC KEY FILE")]
+    pub verify_key: Option<String>,
+}
+
+impl DecodeKeyArgs
+{
+    pub fn plan(&self) -> std::io::Result<DecodePlan>
+    {
+        let signer: Option<VerifyingKey> = self.verify_key.as_deref().map(load_verifying_key).transpose()
+            .map_err(|e| std::io::Error::new(e.kind(), format!("Trusted public key could not be read: {}", e)))?;
+        return Ok(DecodePlan { keys: read_keys(&self.keys)?, identities: self.identities.clone(), shares: self.shares.clone(), signer });
+    }
+}
+
+#[derive(Args)]
+#[command(after_help = KEY_SOURCES)]
+pub struct DecompressArgs
+{
+    #[command(flatten)]
+    pub credentials: DecodeKeyArgs,
+    /// Save the output even when it does not match the stored checksum
+    #[arg(long)]
+    pub ignore_checksum: bool,
+    /// Do not restore the original name, permissions and modification time
+    #[arg(long)]
+    pub no_metadata: bool,
+    /// Encoded files (or any of their volumes) or directories; - decodes standard input to standard output
+    #[arg(required = true)]
+    pub files: Vec<String>,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    #[command(flatten)]
+    batch: BatchArgs,
+}
+
+impl DecompressArgs
+{
+    pub fn is_stdin(&self) -> bool
+    {
+        return self.files.len() == 1 && self.files[0] == STDIO_PATH;
+    }
+
+    pub fn output_options(&self) -> OutputOptions
+    {
+        return self.output.options();
+    }
+
+    pub fn batch_options(&self) -> BatchOptions
+    {
+        return self.batch.options();
+    }
+}
+
+#[derive(Args)]
+#[command(after_help = KEY_SOURCES)]
+pub struct TestArgs
+{
+    #[command(flatten)]
+    pub credentials: DecodeKeyArgs,
+    /// Encoded files (or any of their volumes) or directories
+    #[arg(required = true)]
+    pub files: Vec<String>,
+
+    #[command(flatten)]
+    batch: BatchArgs,
+}
+
+impl TestArgs
+{
+    pub fn batch_options(&self) -> BatchOptions
+    {
+        return self.batch.options();
+    }
+}
+
+#[derive(Args)]
+pub struct BenchArgs
+{
+    /// Pipeline to run, as codec names in order separated by commas or spaces (rle,huffman or "--rle --huffman"); may be repeated
+    #[arg(short, long = "pipeline", value_name = "CODECS", allow_hyphen_values = true, value_parser = parse_pipeline,
+        default_values = ["huffman", "rle", "rle,huffman"])]
+    pub pipelines: Vec<BenchPipeline>,
+    /// Runs of every pipeline; the fastest one is reported
+    #[arg(long, value_name = "N", default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    pub runs: u32,
+    /// Files to run the pipelines on
+    #[arg(required = true)]
+    pub files: Vec<String>,
+}
+
+// Codec stages in the order they are applied; keyed stages run with random keys
+#[derive(Clone)]
+pub struct BenchPipeline(pub Vec<CodecList>);
+
+impl BenchPipeline
+{
+    pub fn describe(&self) -> String
+    {
+        return self.0.iter().map(|codec| format!("--{}", codec_option_name(*codec))).collect::<Vec<String>>().join(" ");
+    }
+}
+
+// Codecs with an id but no implementation; bench reports them as unavailable
+const UNIMPLEMENTED_CODECS: [(&str, CodecList); 2] = [("lz77", CodecList::LZ77), ("arithmetic", CodecList::Arithmetic)];
+
+fn codec_option_name(codec: CodecList) -> &'static str
+{
+    return CODEC_OPTIONS.iter().chain(UNIMPLEMENTED_CODECS.iter()).find(|(_, known)| *known == codec).map(|(name, _)| *name).unwrap_or("unknown");
+}
+
+fn parse_pipeline(arg: &str) -> Result<BenchPipeline, String>
+{
+    let mut stages: Vec<CodecList> = Vec::new();
+    for name in arg.split(|c: char| c == ',' || c.is_whitespace()).filter(|name| !name.is_empty())
+    {
+        let name: &str = name.trim_start_matches('-');
+        let (_, codec) = CODEC_OPTIONS.iter().chain(UNIMPLEMENTED_CODECS.iter()).find(|(known, _)| *known == name)
+            .ok_or_else(|| format!("unknown codec {}", name))?;
+        if matches!(codec, CodecList::X25519 | CodecList::KeySlots) { return Err(String::from("recipient and key slot stages cannot be benchmarked")); }
+        stages.push(*codec);
+    }
+    if stages.is_empty() { return Err(String::from("a pipeline needs at least one codec")); }
+    return Ok(BenchPipeline(stages));
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Batch")]
+struct BatchArgs
+{
+    /// Process the files in directories given, and in their subdirectories
+    #[arg(short, long)]
+    recursive: bool,
+    /// Only process files in directories matching this glob (on the name, or on the path below the directory when it has a /)
+    #[arg(long, value_name = "GLOB", value_parser = parse_glob)]
+    include: Vec<Pattern>,
+    /// Skip files and subdirectories matching this glob
+    #[arg(long, value_name = "GLOB", value_parser = parse_glob)]
+    exclude: Vec<Pattern>,
+}
+
+impl BatchArgs
+{
+    fn options(&self) -> BatchOptions
+    {
+        return BatchOptions { recursive: self.recursive, include: self.include.clone(), exclude: self.exclude.clone() };
+    }
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Output")]
+struct OutputArgs
+{
+    /// Write the result to this path instead of next to the input; - writes standard output
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<String>,
+    /// Overwrite the output if it already exists
+    #[arg(short, long)]
+    force: bool,
+}
+
+impl OutputArgs
+{
+    fn options(&self) -> OutputOptions
+    {
+        return OutputOptions { path: self.output.clone(), force: self.force };
+    }
+}
+
+#[derive(Args)]
+#[command(after_help = KEY_SOURCES)]
+pub struct RangeArgs
+{
+    /// Byte range to decode, as <start>:<end>; a missing end reads to the end of the content
+    #[arg(value_parser = parse_range)]
+    pub range: (u64, Option<u64>),
+    /// Key of an encrypted stage, repeated in decoding order
+    #[arg(short, long = "key", value_parser = parse_secret, value_name = "KEY")]
+    pub keys: Vec<Zeroizing<String>>,
+    /// Chunked .rsz file
+    pub file: String,
+}
+
+#[derive(Args)]
+pub struct KeygenArgs
+{
+    /// Generate an Ed25519 signing keypair instead of an X25519 one
+    #[arg(long)]
+    pub ed25519: bool,
+    /// Name of the keypair files
+    pub name: String,
+}
+
+#[derive(Args)]
+pub struct SignArgs
+{
+    /// Ed25519 private key file
+    #[arg(long = "key", value_name = "KEY FILE")]
+    pub private_key: String,
+    /// Write the signature to <file>.sig instead of embedding it
+    #[arg(long)]
+    pub detached: bool,
+    /// File to sign
+    pub file: String,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs
+{
+    /// Trusted Ed25519 public key file
+    #[arg(long = "key", value_name = "KEY FILE")]
+    pub public_key: String,
+    /// Signed file
+    pub file: String,
+}
+
+#[derive(Args)]
+#[command(after_help = KEY_SOURCES)]
+pub struct RekeyArgs
+{
+    /// Current key of an encrypted stage, repeated in decoding order
+    #[arg(long = "old", value_parser = parse_secret, value_name = "KEY", required = true)]
+    pub old_keys: Vec<Zeroizing<String>>,
+    /// New key, repeated in the same order
+    #[arg(long = "new", value_parser = parse_secret, value_name = "KEY", required = true)]
+    pub new_keys: Vec<Zeroizing<String>>,
+    /// Encoded file
+    pub file: String,
+}
+
+#[derive(Args)]
+pub struct AttackArgs
+{
+    /// Text whose letter frequencies replace the English ones
+    #[arg(long, value_name = "CORPUS FILE")]
+    pub reference: Option<String>,
+    /// Encoded file with a Caesar stage
+    pub file: String,
+}
+
+#[derive(Args)]
+pub struct AddSlotArgs
+{
+    /// Credential opening an existing slot: pw:<password> or a private key file
+    #[arg(long, value_parser = parse_secret, value_name = "CREDENTIAL")]
+    pub unlock: Zeroizing<String>,
+    /// Concealing key, when the header is concealed
+    #[arg(long, value_parser = parse_secret, value_name = "KEY")]
+    pub conceal: Option<Zeroizing<String>>,
+    /// New slot: pw:<password> or a public key file
+    #[arg(value_parser = parse_secret)]
+    pub slot: Zeroizing<String>,
+    /// Encoded file
+    pub file: String,
+}
+
+#[derive(Args)]
+pub struct RemoveSlotArgs
+{
+    /// Credential opening an existing slot: pw:<password> or a private key file
+    #[arg(long, value_parser = parse_secret, value_name = "CREDENTIAL")]
+    pub unlock: Zeroizing<String>,
+    /// Concealing key, when the header is concealed
+    #[arg(long, value_parser = parse_secret, value_name = "KEY")]
+    pub conceal: Option<Zeroizing<String>>,
+    /// Index of the slot to remove, as shown by info
+    pub index: usize,
+    /// Encoded file
+    pub file: String,
+}
+
+#[derive(Args)]
+pub struct SplitKeyArgs
+{
+    /// Credential opening an existing slot: pw:<password> or a private key file
+    #[arg(long, value_parser = parse_secret, value_name = "CREDENTIAL")]
+    pub unlock: Zeroizing<String>,
+    /// Concealing key, when the header is concealed
+    #[arg(long, value_parser = parse_secret, value_name = "KEY")]
+    pub conceal: Option<Zeroizing<String>>,
+    /// Shares needed to rebuild the key
+    #[arg(long, value_name = "K")]
+    pub threshold: u8,
+    /// Shares written, as <file>.share1, <file>.share2...
+    #[arg(long, value_name = "N")]
+    pub shares: u8,
+    /// Encoded file
+    pub file: String,
+}
+
+// Checksum algorithm, or none to store no checksum
+#[derive(Clone, Copy)]
+pub struct ChecksumChoice(Option<ChecksumAlgorithm>);
+
+fn parse_checksum(arg: &str) -> Result<ChecksumChoice, String>
+{
+    if arg == "none" { return Ok(ChecksumChoice(None)); }
+    return ChecksumAlgorithm::from_name(arg).map(|algorithm| ChecksumChoice(Some(algorithm))).ok_or_else(|| String::from("use crc32, xxhash64, blake3 or none"));
+}
+
+fn parse_secret(arg: &str) -> Result<Zeroizing<String>, String>
+{
+    // Keys and passwords are wiped from memory when the arguments are dropped
+    return Ok(Zeroizing::new(arg.to_string()));
+}
+
+fn parse_glob(arg: &str) -> Result<Pattern, String>
+{
+    return Pattern::new(arg).map_err(|e| format!("invalid glob: {}", e));
+}
+
+fn parse_percent(arg: &str) -> Result<u8, String>
+{
+    return arg.trim_end_matches('%').parse::<u8>().ok().filter(|percent| (1..=100).contains(percent))
+        .ok_or_else(|| String::from("use a percentage from 1 to 100"));
+}
+
+fn parse_size(arg: &str) -> Result<usize, String>
+{
+    return parse_byte_size(arg).ok_or_else(|| String::from("use a positive number of bytes, optionally followed by K, M or G"));
+}
+
+fn parse_range(arg: &str) -> Result<(u64, Option<u64>), String>
+{
+    // start:end, where a missing start is 0 and a missing end reads up to the end of the content
+    let invalid = || String::from("use <start>:<end>, for example 1000:2000 or 1000:");
+    let (start, end) = arg.split_once(':').ok_or_else(invalid)?;
+    let start: u64 = if start.is_empty() { 0 } else { start.parse().map_err(|_| invalid())? };
+    let end: Option<u64> = if end.is_empty() { None } else { Some(end.parse().map_err(|_| invalid())?) };
+    return Ok((start, end));
+}
+
+pub fn read_keys(key_args: &[Zeroizing<String>]) -> std::io::Result<Vec<RZ_KEY_TYPE>>
+{
+    // Keys given to decode are not confirmed, a wrong one is reported by the stage it belongs to
+    let mut keys: Vec<RZ_KEY_TYPE> = Vec::with_capacity(key_args.len());
+    for key_arg in key_args
+    {
+        let label: String = format!("Key {}", keys.len() + 1);
+        keys.push(resolve_key(key_arg, &label, false).map_err(|e| std::io::Error::new(e.kind(), format!("{} could not be read: {}", label, e)))?);
+    }
+    return Ok(keys);
+}
+
+pub fn parse_cli() -> Cli
+{
+    // Usage errors are reported by clap, which exits with a message and the usage of the subcommand
+    let matches: ArgMatches = Cli::command().get_matches();
+    let mut cli: Cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let (Command::Compress(args), Some(compress_matches)) = (&mut cli.command, matches.subcommand_matches("compress"))
+    {
+        args.read_pipeline(compress_matches);
+        let compress_command = ||
+        {
+            // Building the command first gives the subcommand its full name for the usage line
+            let mut command: clap::Command = Cli::command();
+            command.build();
+            return command.find_subcommand("compress").expect("Compress is a subcommand").clone();
+        };
+        if args.pipeline.is_empty()
+        {
+            compress_command().error(ClapErrorKind::MissingRequiredArgument, "at least one codec option is needed, for example --huffman").exit();
+        }
+        if args.pipeline.iter().filter(|(codec, _)| matches!(codec, CodecList::KeySlots)).count() > 1
+        {
+            compress_command().error(ClapErrorKind::ArgumentConflict, "only one key slot stage is allowed").exit();
+        }
+    }
+    return cli;
+}
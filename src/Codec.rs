@@ -1,11 +1,12 @@
 use num_enum::TryFromPrimitive;
 
-use crate::{RZ_KEY_TYPE, KEY_LENGTH_BYTES, KEY_LENGTH_BITS};
+use crate::RZ_KEY_TYPE;
 
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, TryFromPrimitive)]
 #[repr(u8)]
 pub enum CodecList
 {
+    // RustyZipper was the one-byte file signature before the versioned header; its id stays reserved
     RustyZipper,
     Huffman,
     RLE,
@@ -13,10 +14,41 @@ pub enum CodecList
     AES,
     LZ77,
     Arithmetic,
+    X25519,
+    KeySlots,
+    Vigenere,
+    XOR,
 }
 
+impl CodecList
+{
+    pub fn needs_key(&self) -> bool
+    {
+        // Stages that consume one of the keys given on the command line
+        return matches!(self, CodecList::Caesar | CodecList::AES | CodecList::Vigenere | CodecList::XOR);
+    }
+}
+
+// Parameter record stored in the container header for one pipeline stage (window size, block size, nonce...)
+pub type StageParams = Vec<u8>;
+
 pub trait CodecFunctions
 {
-    fn encode(data: &Vec<u8>, key: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>;
-    fn decode(encoded_data: &Vec<u8>, key: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>;
+    fn encode(data: &[u8], key: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>;
+    fn decode(encoded_data: &[u8], key: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>;
+
+    // Tunable codecs override these two; the rest record no parameters
+    fn encode_with_params(data: &[u8], key: Option<&RZ_KEY_TYPE>) -> std::io::Result<(Vec<u8>, StageParams)>
+    {
+        return Ok((Self::encode(data, key)?, StageParams::new()));
+    }
+
+    fn decode_with_params(encoded_data: &[u8], key: Option<&RZ_KEY_TYPE>, params: &[u8]) -> std::io::Result<Vec<u8>>
+    {
+        if !params.is_empty()
+        {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Stage has parameters this codec does not understand"));
+        }
+        return Self::decode(encoded_data, key);
+    }
 }
\ No newline at end of file
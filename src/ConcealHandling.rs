@@ -0,0 +1,56 @@
+use std::io::{Error, ErrorKind};
+
+use crate::AESObjects::AESEncoder;
+use crate::Codec::CodecFunctions;
+use crate::HeaderHandling::{serialize_header, parse_fixed_header, FLAG_CONCEALED};
+use crate::RZ_KEY_TYPE;
+
+pub fn is_concealed(buffer: &[u8]) -> bool
+{
+    return parse_fixed_header(buffer).is_ok_and(|header| header.flags & FLAG_CONCEALED != 0);
+}
+
+fn padded_len(len: usize) -> usize
+{
+    // Padmé buckets: lengths are rounded up so that only O(log log n) bits of the real size leak,
+    // with at most ~12% of overhead
+    if len < 2 { return len; }
+    let exponent: u32 = usize::BITS - 1 - len.leading_zeros();
+    let exponent_bits: u32 = u32::BITS - exponent.leading_zeros();
+    let mask: usize = (1usize << (exponent - exponent_bits)) - 1;
+    return (len + mask) & !mask;
+}
+
+pub fn conceal_container(container: &[u8], key: &RZ_KEY_TYPE, pad: bool) -> std::io::Result<Vec<u8>>
+{
+    // Envelope: real container length (u64) + container (with its own signature, codecs and parameters) + zero padding
+    let envelope_len: usize = 8 + container.len();
+    let target_len: usize = if pad { padded_len(envelope_len) } else { envelope_len };
+
+    let mut envelope: Vec<u8> = Vec::with_capacity(target_len);
+    envelope.extend_from_slice(&(container.len() as u64).to_be_bytes());
+    envelope.extend_from_slice(container);
+    envelope.resize(target_len, 0);
+
+    let encrypted: Vec<u8> = AESEncoder::encode(&envelope, Some(key))?;
+    // Outer header only carries the concealed flag
+    let mut concealed: Vec<u8> = serialize_header(FLAG_CONCEALED, &[]);
+    concealed.extend_from_slice(&encrypted);
+    return Ok(concealed);
+}
+
+pub fn reveal_container(buffer: &[u8], key: &RZ_KEY_TYPE) -> std::io::Result<(Vec<u8>, bool)>
+{
+    let header_len: usize = parse_fixed_header(buffer)?.header_len;
+    let mut envelope: Vec<u8> = AESEncoder::decode(&buffer[header_len..], Some(key))?;
+    if envelope.len() < 8 { return Err(Error::new(ErrorKind::InvalidData, "Concealed envelope too short")); }
+
+    let container_len: usize = u64::from_be_bytes(envelope[..8].try_into().expect("Length takes 8 bytes")) as usize;
+    if container_len > envelope.len() - 8 { return Err(Error::new(ErrorKind::InvalidData, "Concealed envelope is truncated")); }
+
+    // Reports whether the envelope was padded, so it can be sealed again the same way
+    let padded: bool = envelope.len() > 8 + container_len;
+    envelope.truncate(8 + container_len);
+    envelope.drain(..8);
+    return Ok((envelope, padded));
+}
@@ -0,0 +1,129 @@
+use std::io::{Error, ErrorKind};
+
+// Relative frequencies of letters a-z in English text
+const ENGLISH_LETTER_FREQUENCIES: [f64; 26] =
+[
+    0.08167, 0.01492, 0.02782, 0.04253, 0.12702, 0.02228, 0.02015, 0.06094, 0.06966, 0.00153, 0.00772, 0.04025, 0.02406,
+    0.06749, 0.07507, 0.01929, 0.00095, 0.05987, 0.06327, 0.09056, 0.02758, 0.00978, 0.02360, 0.00150, 0.01974, 0.00074,
+];
+// Share of letters, spaces and any other byte in typical English prose
+const ENGLISH_LETTER_SHARE: f64 = 0.78;
+const ENGLISH_SPACE_SHARE: f64 = 0.17;
+const ENGLISH_OTHER_SHARE: f64 = 0.05;
+
+// Scoring a prefix is enough to tell shifts apart and keeps the attack fast on big files
+const SCORED_BYTES: usize = 1 << 16;
+
+pub struct ShiftCandidate
+{
+    pub shift: u8,
+    pub chi_squared: f64,
+    pub confidence: f64,
+    pub preview: String,
+}
+
+pub enum ReferenceModel
+{
+    English,
+    Corpus(Vec<f64>),
+}
+
+impl ReferenceModel
+{
+    pub fn from_corpus(corpus: &[u8]) -> std::io::Result<Self>
+    {
+        if corpus.is_empty() { return Err(Error::new(ErrorKind::InvalidInput, "Reference corpus is empty")); }
+
+        // Add-one smoothing, so bytes missing from the corpus do not make the score infinite
+        let mut counts: Vec<f64> = vec![1.0; 256];
+        for &byte in corpus { counts[byte as usize] += 1.0; }
+        let total: f64 = counts.iter().sum();
+        for count in counts.iter_mut() { *count /= total; }
+        return Ok(ReferenceModel::Corpus(counts));
+    }
+
+    fn chi_squared(&self, text: &[u8]) -> f64
+    {
+        let sample: &[u8] = &text[..text.len().min(SCORED_BYTES)];
+        let total: f64 = sample.len() as f64;
+        if sample.is_empty() { return f64::INFINITY; }
+
+        match self
+        {
+            ReferenceModel::English =>
+            {
+                // 26 letters (case folded), space and everything else
+                let mut observed: [f64; 28] = [0.0; 28];
+                for &byte in sample
+                {
+                    match byte.to_ascii_lowercase()
+                    {
+                        letter @ b'a'..=b'z' => { observed[(letter - b'a') as usize] += 1.0; }
+                        b' ' => { observed[26] += 1.0; }
+                        _ => { observed[27] += 1.0; }
+                    }
+                }
+
+                let mut chi_squared: f64 = 0.0;
+                for (i, &count) in observed.iter().enumerate()
+                {
+                    let share: f64 = match i
+                    {
+                        26 => ENGLISH_SPACE_SHARE,
+                        27 => ENGLISH_OTHER_SHARE,
+                        _ => ENGLISH_LETTER_FREQUENCIES[i] * ENGLISH_LETTER_SHARE,
+                    };
+                    let expected: f64 = share * total;
+                    chi_squared += (count - expected).powi(2) / expected;
+                }
+                return chi_squared;
+            }
+            ReferenceModel::Corpus(frequencies) =>
+            {
+                let mut observed: [f64; 256] = [0.0; 256];
+                for &byte in sample { observed[byte as usize] += 1.0; }
+
+                let mut chi_squared: f64 = 0.0;
+                for (count, share) in observed.iter().zip(frequencies.iter())
+                {
+                    let expected: f64 = share * total;
+                    chi_squared += (count - expected).powi(2) / expected;
+                }
+                return chi_squared;
+            }
+        }
+    }
+}
+
+fn preview(text: &[u8]) -> String
+{
+    return text.iter().take(60)
+        .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+        .collect();
+}
+
+pub fn break_caesar(shifted: &[u8], model: &ReferenceModel, decode_inner: impl Fn(&[u8]) -> std::io::Result<Vec<u8>>) -> Vec<ShiftCandidate>
+{
+    // Tries all 256 shifts; stages below Caesar are decoded for each one, and shifts they reject are dropped
+    let mut candidates: Vec<ShiftCandidate> = Vec::with_capacity(256);
+    for shift in 0..=u8::MAX
+    {
+        let unshifted: Vec<u8> = shifted.iter().map(|byte| byte.wrapping_sub(shift)).collect();
+        if let Ok(plaintext) = decode_inner(&unshifted)
+        {
+            let chi_squared: f64 = model.chi_squared(&plaintext);
+            if chi_squared.is_finite() { candidates.push(ShiftCandidate { shift, chi_squared, confidence: 0.0, preview: preview(&plaintext) }); }
+        }
+    }
+
+    candidates.sort_by(|a, b| a.chi_squared.total_cmp(&b.chi_squared));
+
+    // Confidence decays with how much worse a score is than the best one, relative to that best score
+    if let Some(best) = candidates.first().map(|candidate| candidate.chi_squared.max(1.0))
+    {
+        let weights: Vec<f64> = candidates.iter().map(|candidate| (-(candidate.chi_squared - best) / best).exp()).collect();
+        let total: f64 = weights.iter().sum();
+        for (candidate, weight) in candidates.iter_mut().zip(weights) { candidate.confidence = weight / total; }
+    }
+    return candidates;
+}
@@ -1,48 +1,170 @@
-use std::env;
 use std::fs::File;
 use std::fs;
 use std::io::{Read, BufReader, Write, BufWriter, Error, ErrorKind};
+use std::path::Path;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
 
-use crate::Codec::CodecList;
-use crate::{DetHashMap, RZ_KEY_TYPE, KEY_LENGTH_BYTES, KEY_LENGTH_BITS};
+use crate::ChecksumHandling::ChecksumAlgorithm;
+use crate::RecoveryHandling::add_recovery_record;
+use crate::VolumeHandling::write_volumes;
 
-pub fn write_decoded_file(filename: &str, decoded_data: &[u8]) -> std::io::Result<()>
+// Stands for standard input when encoding or decoding, or for standard output as an output path
+pub const STDIO_PATH: &str = "-";
+
+fn already_exists(filename: &str) -> Error
 {
-    // Remove .rsz extension to restore original filename
-    let original_filename = if filename.ends_with(".rsz") { &filename[..filename.len() - 4] } 
-    else { filename };
-    
-    let mut file = File::create(original_filename)?;
-    file.write_all(decoded_data);
+    return Error::new(ErrorKind::AlreadyExists, format!("{} already exists; use --force to overwrite it", filename));
+}
 
-    // Remove .rsz file
-    if filename.ends_with(".rsz") { fs::remove_file(filename)?; }
+// Output written to a temporary file next to its destination and only renamed over it once complete,
+// so a failure never leaves a half-written file behind; "-" writes standard output instead
+pub struct OutputFile
+{
+    path: String,
+    temp_path: String,
+    writer: Option<BufWriter<Box<dyn Write>>>,
+    overwrite: bool,
+    committed: bool,
+}
 
-    return Ok(());
+impl OutputFile
+{
+    pub fn create(filename: &str, overwrite: bool) -> std::io::Result<Self>
+    {
+        if filename == STDIO_PATH
+        {
+            let writer: BufWriter<Box<dyn Write>> = BufWriter::new(Box::new(std::io::stdout().lock()));
+            return Ok(OutputFile { path: filename.to_string(), temp_path: String::new(), writer: Some(writer), overwrite, committed: false });
+        }
+
+        // Checked now so nothing is encoded for nothing, and again when renaming
+        if !overwrite && Path::new(filename).exists() { return Err(already_exists(filename)); }
+        let temp_path: String = format!("{}.{}.tmp", filename, std::process::id());
+        let file: File = fs::OpenOptions::new().write(true).create_new(true).open(&temp_path)?;
+        return Ok(OutputFile { path: filename.to_string(), temp_path, writer: Some(BufWriter::new(Box::new(file))), overwrite, committed: false });
+    }
+
+    pub fn is_stdout(&self) -> bool
+    {
+        return self.path == STDIO_PATH;
+    }
+
+    pub fn commit(self) -> std::io::Result<()>
+    {
+        return self.commit_with(|_| Ok(()));
+    }
+
+    pub fn commit_with(mut self, finish: impl FnOnce(&str) -> std::io::Result<()>) -> std::io::Result<()>
+    {
+        // The temporary file is finished (synced, and given its metadata by finish) before it takes the final name
+        let mut writer: BufWriter<Box<dyn Write>> = self.writer.take().expect("Output is committed once");
+        writer.flush()?;
+        drop(writer);
+        if self.is_stdout() { self.committed = true; return Ok(()); }
+        File::open(&self.temp_path)?.sync_all()?;
+        finish(&self.temp_path)?;
+
+        if self.overwrite { fs::rename(&self.temp_path, &self.path)?; }
+        else
+        {
+            // Unlike a rename, a hard link never replaces a file that appeared in the meantime
+            match fs::hard_link(&self.temp_path, &self.path)
+            {
+                Ok(()) => { fs::remove_file(&self.temp_path)?; }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => { return Err(already_exists(&self.path)); }
+                Err(_) if Path::new(&self.path).exists() => { return Err(already_exists(&self.path)); }
+                Err(_) => { fs::rename(&self.temp_path, &self.path)?; }
+            }
+        }
+        self.committed = true;
+        return Ok(());
+    }
 }
 
-pub fn write_encoded_file(filename: &str, buffer: &[u8], codecs: &[u8]) -> std::io::Result<String> 
+impl Write for OutputFile
 {
-    let full_path = format!("{}.rsz", filename);
-    let mut file = BufWriter::new(File::create(&full_path)?);
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize>
+    {
+        return self.writer.as_mut().expect("Output is not written after being committed").write(buf);
+    }
 
-    // Writes metadata
-    let offset = 1 + codecs.len();
-    let mut full_buffer = Vec::with_capacity(offset + buffer.len());
+    fn flush(&mut self) -> std::io::Result<()>
+    {
+        return self.writer.as_mut().expect("Output is not written after being committed").flush();
+    }
+}
 
-    // Writes RustyZipper signature
-    let rszSignature: u8 = CodecList::RustyZipper as u8;
-    full_buffer.push(rszSignature);
+impl Drop for OutputFile
+{
+    fn drop(&mut self)
+    {
+        if !self.committed && !self.is_stdout()
+        {
+            self.writer = None;
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
 
-    // Writes number of codecs used and codecs ids
-    full_buffer.push(codecs.len() as u8);
-    full_buffer.extend_from_slice(codecs);
+pub fn write_file_atomically(filename: &str, content: &[u8], overwrite: bool) -> std::io::Result<()>
+{
+    let mut file: OutputFile = OutputFile::create(filename, overwrite)?;
+    file.write_all(content)?;
+    return file.commit();
+}
 
-    // Writes encoded data
-    full_buffer.extend_from_slice(buffer);
+pub fn write_encoded_file(filename: &str, container: &[u8], header_options: &HeaderOptions, overwrite: bool) -> std::io::Result<usize> 
+{
+    // Returns the number of bytes written, volume headers included
+    // Parity goes around the final container, so damage anywhere in it, header included, can be repaired
+    let recovered: Vec<u8>;
+    let content: &[u8] = match header_options.recovery
+    {
+        Some(redundancy) => { recovered = add_recovery_record(container, redundancy)?; &recovered }
+        None => container,
+    };
 
-    file.write_all(&full_buffer)?;
-    return Ok(full_path);
+    // Split files are written as file.rsz.001, .002... instead of file.rsz
+    if let Some(volume_size) = header_options.volume_size
+    {
+        if filename == STDIO_PATH { return Err(Error::new(ErrorKind::InvalidInput, "Volumes cannot be written to standard output")); }
+        let volumes: Vec<String> = write_volumes(filename, content, volume_size, overwrite)?;
+        println!("Encoded file split into {} volume(s): {} to {}.", volumes.len(), volumes[0], volumes[volumes.len() - 1]);
+        let mut written: usize = 0;
+        for volume in &volumes { written += fs::metadata(volume)?.len() as usize; }
+        return Ok(written);
+    }
+    write_file_atomically(filename, content, overwrite)?;
+    return Ok(content.len());
+}
+
+fn keep_file_attributes(original: &fs::Metadata, temp_path: &str) -> std::io::Result<()>
+{
+    // Changing the owner needs privileges, so without them the replacement belongs to whoever runs RustyZipper
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if std::os::unix::fs::chown(temp_path, Some(original.uid()), Some(original.gid())).is_err()
+        {
+            std::os::unix::fs::chown(temp_path, None, Some(original.gid())).ok();
+        }
+    }
+    // Set after the owner, as changing it may clear the setuid and setgid bits
+    return fs::set_permissions(temp_path, original.permissions());
+}
+
+pub fn replace_file_atomically(filename: &str, content: &[u8]) -> std::io::Result<()>
+{
+    // The replacement takes the permissions, and where allowed the owner, of the file it replaces
+    let original: Option<fs::Metadata> = fs::metadata(filename).ok();
+    let mut file: OutputFile = OutputFile::create(filename, true)?;
+    file.write_all(content)?;
+    return file.commit_with(|temp_path| match &original
+    {
+        Some(original) => keep_file_attributes(original, temp_path),
+        None => Ok(()),
+    });
 }
 
 pub fn read_file(filename: &str) -> std::io::Result<(Vec<u8>, usize)>
@@ -54,90 +176,73 @@ pub fn read_file(filename: &str) -> std::io::Result<(Vec<u8>, usize)>
     return Ok((buffer, len));
 }
 
-pub fn validate_encoded_file(first_byte: u8) -> std::io::Result<()>
+pub fn to_hex(bytes: &[u8]) -> String
 {
-    // Verifies RustyZipper signature
-    if first_byte != CodecList::RustyZipper as u8 { return Err(Error::new(ErrorKind::InvalidData, "File has not been encoded using RustyZipper")); }
-    return Ok(());
+    let mut hex: String = String::with_capacity(bytes.len() * 2);
+    for byte in bytes { hex.push_str(&format!("{:02x}", byte)); }
+    return hex;
 }
 
-pub fn check_entry() -> Option<(String, String, Option<Vec<u8>>, Option<Vec<RZ_KEY_TYPE>>)> 
+pub fn from_hex(hex: &str) -> std::io::Result<Vec<u8>>
 {
-    let args: Vec<String> = env::args().collect();
-
-    if args.len() >= 3
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() { return Err(Error::new(ErrorKind::InvalidData, "Invalid hexadecimal string")); }
+    let mut bytes: Vec<u8> = Vec::with_capacity(hex.len() / 2);
+    for i in (0..hex.len()).step_by(2)
     {
-        let mode: &String = &args[1];
-        let filepath: &String = &args[args.len()-1];
+        let byte = u8::from_str_radix(&hex[i..i + 2], 16)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "Invalid hexadecimal string"))?;
+        bytes.push(byte);
+    }
+    return Ok(bytes);
+}
 
-        let mut keys: Vec<RZ_KEY_TYPE> = Vec::new();
+pub fn write_key_file(filename: &str, label: &str, key_bytes: &[u8], private: bool) -> std::io::Result<()>
+{
+    // Key files are a single "<label>:<hex>" line, so they can be pasted and mailed around
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if private { options.mode(0o600); }
+    #[cfg(not(unix))]
+    let _ = private;
+
+    let mut file = options.open(filename)?;
+    writeln!(file, "{}:{}", label, to_hex(key_bytes))?;
+    return Ok(());
+}
 
-        match mode.as_str()
-        {
-            "-e" => 
-            {
-                let mut codecs: Vec<u8> = Vec::new();
-                let mut key_needed: bool = false;
-                if args.len() == 3
-                {
-                    eprintln!("Incorrect use. Indicate desired codecs after -e");
-                    return None;
-                } 
-                for arg in &args[2..args.len()-1]
-                {
-                    if key_needed
-                    {
-                        // Take up to 16 bytes and pack them into an i128 (big-endian)
-                        let key_bytes = arg.as_bytes();
-                        let mut arr = [0u8; 16];
-                        let n = key_bytes.len().min(16);
-                        arr[16 - n..].copy_from_slice(&key_bytes[..n]);
-                        let key: RZ_KEY_TYPE = i128::from_be_bytes(arr);
-                        keys.push(key);
-                        key_needed = false;
-                        continue;
-                    }
-                    match arg.as_str()
-                    {
-                        "--huffman" => { codecs.push(CodecList::Huffman as u8); }
-                        "--rle" => { codecs.push(CodecList::RLE as u8); }
-                        "--caesar" => 
-                        { 
-                            codecs.push(CodecList::Caesar as u8);
-                            key_needed = true;
-                        }
-                        "--aes" => 
-                        { 
-                            codecs.push(CodecList::AES as u8);
-                            key_needed = true;
-                        }
-                        _ =>
-                        {
-                            eprintln!("Incorrect codec: {}", arg);
-                            return None;
-                        }
-                    }
-                }
-                return Some((mode.clone(), filepath.clone(), Some(codecs), Some(keys)));
-            }
-            "-d" => 
-            { 
-                for arg in &args[2..args.len()-1]
-                {
-                    // Take up to 16 bytes and pack them into an i128 (big-endian)
-                    let key_bytes = arg.as_bytes();
-                    let mut arr = [0u8; 16];
-                    let n = key_bytes.len().min(16);
-                    arr[16 - n..].copy_from_slice(&key_bytes[..n]);
-                    let key: RZ_KEY_TYPE = i128::from_be_bytes(arr);
-                    keys.push(key);
-                }
-                return Some((mode.clone(), filepath.clone(), None, Some(keys)));
-            }
-            _ => {}
-        }
+pub fn read_key_file(filename: &str, label: &str) -> std::io::Result<Vec<u8>>
+{
+    let content: String = fs::read_to_string(filename)?;
+    match content.trim().split_once(':')
+    {
+        Some((file_label, hex)) if file_label == label => { return from_hex(hex); }
+        _ => { return Err(Error::new(ErrorKind::InvalidData, format!("{} is not a {} key file", filename, label))); }
     }
+}
 
-    eprintln!("Incorrect use. Sintax: {} [-e [codecs]|-d] <path to file>", args[0]);
-    return None;
-}
\ No newline at end of file
+// Where the result is written and whether an existing file may be replaced; input files are never removed
+pub struct OutputOptions
+{
+    pub path: Option<String>,
+    pub force: bool,
+}
+
+// How the container is framed and what it records about the original content; decoding only looks at metadata, to skip restoring it
+pub struct HeaderOptions
+{
+    pub checksum: Option<ChecksumAlgorithm>,
+    pub metadata: bool,
+    pub xattrs: bool,
+    pub chunk_size: Option<usize>,
+    pub recovery: Option<u8>,
+    pub volume_size: Option<usize>,
+}
+
+impl Default for HeaderOptions
+{
+    fn default() -> Self
+    {
+        return HeaderOptions { checksum: Some(ChecksumAlgorithm::CRC32), metadata: true, xattrs: false, chunk_size: None, recovery: None, volume_size: None };
+    }
+}
@@ -1,13 +1,57 @@
+use std::fmt;
+
 use sha2::{Sha256, Digest};
 use sha2::digest::Output;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::{RZ_KEY_TYPE, KEY_LENGTH_BYTES};
+
+const PASSWORD_HASH_ROUNDS: usize = 100_000;
+
+// Key bytes are wiped when the key is dropped and never shown by Debug
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct SecretKey([u8; KEY_LENGTH_BYTES]);
+
+impl SecretKey
+{
+    pub fn from_bytes(key_bytes: &[u8; KEY_LENGTH_BYTES]) -> Self
+    {
+        return SecretKey(*key_bytes);
+    }
+
+    pub fn random() -> std::io::Result<Self>
+    {
+        let mut key: SecretKey = SecretKey([0u8; KEY_LENGTH_BYTES]);
+        getrandom::getrandom(&mut key.0)
+            .map_err(|e| std::io::Error::other(format!("Could not gather randomness: {}", e)))?;
+        return Ok(key);
+    }
+
+    pub fn as_bytes(&self) -> &[u8; KEY_LENGTH_BYTES]
+    {
+        return &self.0;
+    }
+}
+
+impl fmt::Debug for SecretKey
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        return write!(f, "SecretKey(<redacted>)");
+    }
+}
 
-use crate::{RZ_KEY_TYPE, KEY_LENGTH_BYTES, KEY_LENGTH_BITS};
+pub fn wipe_and_replace(buffer: &mut Vec<u8>, new_data: Vec<u8>)
+{
+    // Stage buffers may hold plaintext, so they are overwritten before being freed
+    buffer.zeroize();
+    *buffer = new_data;
+}
 
 pub fn hash_key(key: Option<&RZ_KEY_TYPE>) -> ([u8; 32], &RZ_KEY_TYPE)
 {
     let key_ref: &RZ_KEY_TYPE = key.expect("Error: No key");
-    let key_bytes: [u8; 16] = key_ref.to_be_bytes();
-    let key_hashed_raw: Output<Sha256> = Sha256::digest(&key_bytes);
+    let key_hashed_raw: Output<Sha256> = Sha256::digest(key_ref.as_bytes());
     let mut key_hashed: [u8; 32] = [0u8; 32];
     key_hashed.copy_from_slice(key_hashed_raw.as_slice());
     return (key_hashed, key_ref);
@@ -15,7 +59,24 @@ pub fn hash_key(key: Option<&RZ_KEY_TYPE>) -> ([u8; 32], &RZ_KEY_TYPE)
 
 pub fn verify_hash(possible_key: &RZ_KEY_TYPE, hashed_key: &[u8]) -> bool
 {  
-    let possible_key_bytes: [u8; 16] = possible_key.to_be_bytes();
-    let possible_key_hashed: Output<Sha256> = Sha256::digest(&possible_key_bytes);
+    let possible_key_hashed: Output<Sha256> = Sha256::digest(possible_key.as_bytes());
     return possible_key_hashed.as_slice() == hashed_key;
-}   
\ No newline at end of file
+}   
+
+pub fn random_bytes<const N: usize>() -> std::io::Result<[u8; N]>
+{
+    let mut bytes: [u8; N] = [0u8; N];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| std::io::Error::other(format!("Could not gather randomness: {}", e)))?;
+    return Ok(bytes);
+}
+
+pub fn derive_password_key(password: &RZ_KEY_TYPE, salt: &[u8]) -> Zeroizing<[u8; 16]>
+{
+    // Iterated salted SHA-256, so every password guess against a key slot costs many hashes
+    let mut digest: Zeroizing<[u8; 32]> = Zeroizing::new(Sha256::new().chain_update(salt).chain_update(password.as_bytes()).finalize().into());
+    for _ in 1..PASSWORD_HASH_ROUNDS { *digest = Sha256::new().chain_update(salt).chain_update(*digest).finalize().into(); }
+    let mut derived_key: Zeroizing<[u8; 16]> = Zeroizing::new([0u8; 16]);
+    derived_key.copy_from_slice(&digest[..16]);
+    return derived_key;
+}
@@ -0,0 +1,395 @@
+use std::io::{Error, ErrorKind};
+
+use crate::Codec::{CodecList, StageParams};
+use crate::KeySlotObjects::KeySlotTable;
+
+// Container layout: magic, major and minor version, flags (u16), total header length (u32), header body, payload
+pub const FORMAT_MAGIC: &[u8; 8] = b"\x89RSZ\r\n\x1a\n";
+pub const FORMAT_MAJOR_VERSION: u8 = 1;
+pub const FORMAT_MINOR_VERSION: u8 = 0;
+const FIXED_HEADER_LEN: usize = 8 + 2 + 2 + 4;
+
+// Concealed files carry no codec list: their payload is the encrypted real container
+pub const FLAG_CONCEALED: u16 = 1 << 0;
+// Chunked payloads run the pipeline on every chunk on its own and end with a seek table
+pub const FLAG_CHUNKED: u16 = 1 << 1;
+// Streamed payloads run the pipeline on every chunk too, but frame each chunk on its own instead of indexing them at the end
+pub const FLAG_STREAMED: u16 = 1 << 2;
+const KNOWN_FLAGS: u16 = FLAG_CONCEALED | FLAG_CHUNKED | FLAG_STREAMED;
+
+// Header fields with this bit set in their type may be skipped by readers that do not know them
+pub const FIELD_OPTIONAL: u8 = 0x80;
+
+pub struct HeaderField
+{
+    pub kind: u8,
+    pub value: Vec<u8>,
+}
+
+pub fn serialize_header(flags: u16, body: &[u8]) -> Vec<u8>
+{
+    let mut header: Vec<u8> = Vec::with_capacity(FIXED_HEADER_LEN + body.len());
+    header.extend_from_slice(FORMAT_MAGIC);
+    header.push(FORMAT_MAJOR_VERSION);
+    header.push(FORMAT_MINOR_VERSION);
+    header.extend_from_slice(&flags.to_be_bytes());
+    header.extend_from_slice(&((FIXED_HEADER_LEN + body.len()) as u32).to_be_bytes());
+    header.extend_from_slice(body);
+    return header;
+}
+
+pub fn serialize_encoded_file(buffer: &[u8], flags: u16, codecs: &[u8], stage_params: &[StageParams], key_slots: Option<&KeySlotTable>, fields: &[HeaderField]) -> Vec<u8>
+{
+    // Header body: number of codecs used, then every codec id with its parameter record (u16 length + bytes)
+    let mut body: Vec<u8> = Vec::with_capacity(1 + 3 * codecs.len());
+    body.push(codecs.len() as u8);
+    for (&codec, params) in codecs.iter().zip(stage_params)
+    {
+        body.push(codec);
+        body.extend_from_slice(&(params.len() as u16).to_be_bytes());
+        body.extend_from_slice(params);
+    }
+
+    // Key slot table, only present when a key slot stage is used
+    if let Some(table) = key_slots { body.extend_from_slice(&table.serialize()); }
+
+    // Remaining fields as type (u8), length (u16) and value
+    for field in fields
+    {
+        body.push(field.kind);
+        body.extend_from_slice(&(field.value.len() as u16).to_be_bytes());
+        body.extend_from_slice(&field.value);
+    }
+
+    // Writes header followed by encoded data
+    let mut full_buffer: Vec<u8> = serialize_header(flags, &body);
+    full_buffer.extend_from_slice(buffer);
+    return full_buffer;
+}
+
+pub struct EncodedHeader
+{
+    pub flags: u16,
+    pub codecs: Vec<u8>,
+    pub stage_params: Vec<StageParams>,
+    pub key_slots: Option<KeySlotTable>,
+    pub fields: Vec<HeaderField>,
+    pub payload_offset: usize,
+}
+
+impl EncodedHeader
+{
+    pub fn keys_needed(&self) -> usize
+    {
+        // Keys given on the command line, besides the one a concealed header takes and key slot passwords
+        return self.codecs.iter().filter(|&&codec| CodecList::try_from(codec).is_ok_and(|stage| stage.needs_key())).count();
+    }
+}
+
+pub struct FixedHeader<'a>
+{
+    pub version: (u8, u8),
+    pub flags: u16,
+    pub body: &'a [u8],
+    pub header_len: usize,
+}
+
+fn malformed(message: String) -> Error
+{
+    return Error::new(ErrorKind::InvalidData, message);
+}
+
+fn is_unversioned_container(buffer: &[u8]) -> bool
+{
+    // Before the versioned header, files started with the RustyZipper codec id, the codec count and the codec ids
+    let codecs_len: usize = match buffer
+    {
+        [signature, codecs_len, ..] if *signature == CodecList::RustyZipper as u8 && *codecs_len > 0 => *codecs_len as usize,
+        _ => { return false; }
+    };
+    return buffer.get(2..2 + codecs_len).is_some_and(|codecs| codecs.iter()
+        .all(|&codec| matches!(CodecList::try_from(codec), Ok(CodecList::Huffman | CodecList::RLE | CodecList::Caesar | CodecList::AES | CodecList::LZ77 | CodecList::Arithmetic))));
+}
+
+pub fn parse_fixed_header(buffer: &[u8]) -> std::io::Result<FixedHeader<'_>>
+{
+    // Checks magic and version before handing out flags, header body and total header length
+    if buffer.is_empty() { return Err(malformed(String::from("File is empty"))); }
+    if is_unversioned_container(buffer)
+    {
+        return Err(malformed(format!("File was written by an older RustyZipper, before the versioned container format; decode it with that version and encode it again with RustyZipper {}", env!("CARGO_PKG_VERSION"))));
+    }
+    if !buffer.starts_with(FORMAT_MAGIC) && !FORMAT_MAGIC.starts_with(buffer) { return Err(malformed(String::from("File has not been encoded using RustyZipper"))); }
+    let fixed: &[u8] = buffer.get(..FIXED_HEADER_LEN)
+        .ok_or_else(|| malformed(format!("File is truncated: it is {} bytes long, but the fixed header alone takes {}", buffer.len(), FIXED_HEADER_LEN)))?;
+
+    let (major_version, minor_version) = (fixed[8], fixed[9]);
+    if major_version != FORMAT_MAJOR_VERSION
+    {
+        return Err(malformed(format!("File format version {}.{} is not supported; this version of RustyZipper reads {}.x", major_version, minor_version, FORMAT_MAJOR_VERSION)));
+    }
+
+    let flags: u16 = u16::from_be_bytes([fixed[10], fixed[11]]);
+    if flags & !KNOWN_FLAGS != 0 { return Err(malformed(format!("File uses unsupported header flags: {:#06x}", flags & !KNOWN_FLAGS))); }
+
+    let header_len: usize = u32::from_be_bytes([fixed[12], fixed[13], fixed[14], fixed[15]]) as usize;
+    if header_len < FIXED_HEADER_LEN
+    {
+        return Err(malformed(format!("File header declares a length of {} bytes, shorter than its {} byte fixed part", header_len, FIXED_HEADER_LEN)));
+    }
+    if header_len > buffer.len()
+    {
+        return Err(malformed(format!("File header declares a length of {} bytes, but the file is only {} bytes long: it is truncated", header_len, buffer.len())));
+    }
+
+    return Ok(FixedHeader { version: (major_version, minor_version), flags, body: &buffer[FIXED_HEADER_LEN..header_len], header_len });
+}
+
+fn check_stage_codec(codec_byte: u8, stage_number: usize) -> std::io::Result<()>
+{
+    match CodecList::try_from(codec_byte)
+    {
+        Ok(CodecList::RustyZipper) => { return Err(malformed(format!("Stage {} uses codec id {}, which is reserved", stage_number, codec_byte))); }
+        Ok(codec @ (CodecList::LZ77 | CodecList::Arithmetic)) => { return Err(malformed(format!("Stage {} uses {:?}, which this version of RustyZipper does not implement", stage_number, codec))); }
+        Ok(_) => { return Ok(()); }
+        Err(_) => { return Err(malformed(format!("Stage {} uses unknown codec id {}", stage_number, codec_byte))); }
+    }
+}
+
+pub fn parse_encoded_header(buffer: &[u8]) -> std::io::Result<EncodedHeader>
+{
+    // Every length is checked against what is left of the header body before it is used
+    let FixedHeader { flags, body, header_len, .. } = parse_fixed_header(buffer)?;
+    if flags & FLAG_CONCEALED != 0 { return Err(malformed(String::from("File header is concealed and its key is needed to read it"))); }
+
+    let codecs_len: usize = *body.first().ok_or_else(|| malformed(String::from("File header is truncated: the codec count is missing")))? as usize;
+    let mut codecs: Vec<u8> = Vec::with_capacity(codecs_len);
+    let mut stage_params: Vec<StageParams> = Vec::with_capacity(codecs_len);
+    let mut current_byte: usize = 1;
+    for i_stage in 0..codecs_len
+    {
+        let stage_header: &[u8] = body.get(current_byte..current_byte + 3)
+            .ok_or_else(|| malformed(format!("File header is truncated: stage {} of {} is missing its codec id or parameter length", i_stage + 1, codecs_len)))?;
+        check_stage_codec(stage_header[0], i_stage + 1)?;
+        let params_len: usize = u16::from_be_bytes([stage_header[1], stage_header[2]]) as usize;
+        let params: &[u8] = body.get(current_byte + 3..current_byte + 3 + params_len)
+            .ok_or_else(|| malformed(format!("File header is truncated: stage {} declares {} parameter bytes, but only {} are left",
+                i_stage + 1, params_len, body.len() - (current_byte + 3).min(body.len()))))?;
+        codecs.push(stage_header[0]);
+        stage_params.push(params.to_vec());
+        current_byte += 3 + params_len;
+    }
+
+    let mut key_slots: Option<KeySlotTable> = None;
+    let key_slot_stages: usize = codecs.iter().filter(|&&codec| codec == CodecList::KeySlots as u8).count();
+    if key_slot_stages > 1 { return Err(malformed(format!("File header has {} key slot stages, but only one is allowed", key_slot_stages))); }
+    if key_slot_stages == 1
+    {
+        let (table, table_len) = KeySlotTable::deserialize(&body[current_byte..])
+            .map_err(|e| malformed(format!("File header has a malformed key slot table: {}", e)))?;
+        key_slots = Some(table);
+        current_byte += table_len;
+    }
+
+    // Fields are kept for their users (and for rewriting); no required field types are defined yet, so those are refused
+    let mut fields: Vec<HeaderField> = Vec::new();
+    while current_byte < body.len()
+    {
+        let field_offset: usize = FIXED_HEADER_LEN + current_byte;
+        let field_header: &[u8] = body.get(current_byte..current_byte + 3)
+            .ok_or_else(|| malformed(format!("File header is truncated: the field at offset {} is missing its type or length", field_offset)))?;
+        let kind: u8 = field_header[0];
+        let value_len: usize = u16::from_be_bytes([field_header[1], field_header[2]]) as usize;
+        let value: &[u8] = body.get(current_byte + 3..current_byte + 3 + value_len)
+            .ok_or_else(|| malformed(format!("File header is truncated: the field at offset {} declares {} bytes, but only {} are left",
+                field_offset, value_len, body.len() - current_byte - 3)))?;
+        if kind & FIELD_OPTIONAL == 0
+        {
+            return Err(malformed(format!("File header has an unknown required field: {}", kind)));
+        }
+        fields.push(HeaderField { kind, value: value.to_vec() });
+        current_byte += 3 + value_len;
+    }
+
+    return Ok(EncodedHeader { flags, codecs, stage_params, key_slots, fields, payload_offset: header_len });
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn error_of(buffer: &[u8]) -> String
+    {
+        match parse_encoded_header(buffer)
+        {
+            Ok(_) => { panic!("Malformed header was accepted"); }
+            Err(e) => { return e.to_string(); }
+        }
+    }
+
+    fn with_fixed_header(major_version: u8, flags: u16, header_len: u32) -> Vec<u8>
+    {
+        let mut header: Vec<u8> = FORMAT_MAGIC.to_vec();
+        header.extend_from_slice(&[major_version, FORMAT_MINOR_VERSION]);
+        header.extend_from_slice(&flags.to_be_bytes());
+        header.extend_from_slice(&header_len.to_be_bytes());
+        return header;
+    }
+
+    fn valid_container() -> Vec<u8>
+    {
+        let fields: Vec<HeaderField> = vec![HeaderField { kind: FIELD_OPTIONAL | 0x7f, value: b"value".to_vec() }];
+        return serialize_encoded_file(b"payload", FLAG_CHUNKED, &[CodecList::Huffman as u8, CodecList::AES as u8], &[vec![], vec![1, 2]], None, &fields);
+    }
+
+    #[test]
+    fn valid_header_is_parsed()
+    {
+        let container: Vec<u8> = valid_container();
+        let header: EncodedHeader = parse_encoded_header(&container).expect("Valid header is accepted");
+        assert_eq!(header.flags, FLAG_CHUNKED);
+        assert_eq!(header.codecs, vec![CodecList::Huffman as u8, CodecList::AES as u8]);
+        assert_eq!(header.stage_params, vec![vec![], vec![1, 2]]);
+        assert_eq!(header.fields.len(), 1);
+        assert_eq!(&container[header.payload_offset..], b"payload");
+        assert_eq!(header.keys_needed(), 1);
+    }
+
+    #[test]
+    fn empty_file_is_refused()
+    {
+        assert!(error_of(&[]).contains("empty"));
+    }
+
+    #[test]
+    fn other_files_are_refused()
+    {
+        assert!(error_of(b"plain text, not an archive").contains("not been encoded using RustyZipper"));
+    }
+
+    #[test]
+    fn truncated_magic_is_refused()
+    {
+        assert!(error_of(&FORMAT_MAGIC[..3]).contains("truncated"));
+    }
+
+    #[test]
+    fn truncated_fixed_header_is_refused()
+    {
+        assert!(error_of(&valid_container()[..12]).contains("fixed header alone takes 16"));
+    }
+
+    #[test]
+    fn unsupported_major_version_is_refused()
+    {
+        assert!(error_of(&with_fixed_header(FORMAT_MAJOR_VERSION + 1, 0, 16)).contains("version 2.0 is not supported"));
+    }
+
+    #[test]
+    fn unknown_flags_are_refused()
+    {
+        assert!(error_of(&with_fixed_header(FORMAT_MAJOR_VERSION, 1 << 15, 16)).contains("unsupported header flags: 0x8000"));
+    }
+
+    #[test]
+    fn header_length_below_fixed_part_is_refused()
+    {
+        assert!(error_of(&with_fixed_header(FORMAT_MAJOR_VERSION, 0, 15)).contains("shorter than its 16 byte fixed part"));
+    }
+
+    #[test]
+    fn header_length_past_end_is_refused()
+    {
+        assert!(error_of(&with_fixed_header(FORMAT_MAJOR_VERSION, 0, 17)).contains("only 16 bytes long"));
+    }
+
+    #[test]
+    fn concealed_header_is_refused()
+    {
+        assert!(error_of(&serialize_header(FLAG_CONCEALED, &[])).contains("concealed"));
+    }
+
+    #[test]
+    fn missing_codec_count_is_refused()
+    {
+        assert!(error_of(&serialize_header(0, &[])).contains("codec count is missing"));
+    }
+
+    #[test]
+    fn truncated_stage_is_refused()
+    {
+        assert!(error_of(&serialize_header(0, &[2, CodecList::RLE as u8, 0, 0, CodecList::RLE as u8])).contains("stage 2 of 2 is missing"));
+    }
+
+    #[test]
+    fn truncated_stage_parameters_are_refused()
+    {
+        assert!(error_of(&serialize_header(0, &[1, CodecList::RLE as u8, 0, 5, 1, 2])).contains("declares 5 parameter bytes, but only 2 are left"));
+    }
+
+    #[test]
+    fn unknown_codec_is_refused()
+    {
+        assert!(error_of(&serialize_header(0, &[1, 200, 0, 0])).contains("unknown codec id 200"));
+    }
+
+    #[test]
+    fn reserved_codec_is_refused()
+    {
+        assert!(error_of(&serialize_header(0, &[1, CodecList::RustyZipper as u8, 0, 0])).contains("reserved"));
+    }
+
+    #[test]
+    fn unversioned_container_is_reported()
+    {
+        let legacy: [u8; 6] = [CodecList::RustyZipper as u8, 2, CodecList::Huffman as u8, CodecList::AES as u8, 0x12, 0x34];
+        assert!(error_of(&legacy).contains("older RustyZipper"));
+    }
+
+    #[test]
+    fn unimplemented_codec_is_refused()
+    {
+        assert!(error_of(&serialize_header(0, &[1, CodecList::LZ77 as u8, 0, 0])).contains("does not implement"));
+    }
+
+    #[test]
+    fn repeated_key_slot_stages_are_refused()
+    {
+        let key_slots: u8 = CodecList::KeySlots as u8;
+        assert!(error_of(&serialize_header(0, &[2, key_slots, 0, 0, key_slots, 0, 0])).contains("2 key slot stages"));
+    }
+
+    #[test]
+    fn truncated_key_slot_table_is_refused()
+    {
+        assert!(error_of(&serialize_header(0, &[1, CodecList::KeySlots as u8, 0, 0, 1])).contains("malformed key slot table"));
+    }
+
+    #[test]
+    fn truncated_field_is_refused()
+    {
+        assert!(error_of(&serialize_header(0, &[0, FIELD_OPTIONAL | 1, 0])).contains("field at offset 17 is missing"));
+    }
+
+    #[test]
+    fn truncated_field_value_is_refused()
+    {
+        assert!(error_of(&serialize_header(0, &[0, FIELD_OPTIONAL | 1, 0, 4, 1])).contains("declares 4 bytes, but only 1 are left"));
+    }
+
+    #[test]
+    fn unknown_required_field_is_refused()
+    {
+        assert!(error_of(&serialize_header(0, &[0, 0x01, 0, 0])).contains("unknown required field: 1"));
+    }
+
+    #[test]
+    fn every_truncated_header_is_refused()
+    {
+        // Any prefix shorter than the header must fail with an error, never panic
+        let container: Vec<u8> = valid_container();
+        let header_len: usize = parse_encoded_header(&container).expect("Valid header is accepted").payload_offset;
+        for len in 0..header_len { assert!(parse_encoded_header(&container[..len]).is_err(), "Prefix of {} bytes was accepted", len); }
+    }
+}
@@ -1,10 +1,11 @@
 use std::collections::BinaryHeap;
-use std::cmp::Ordering;
 use std::fmt;
-use std::io::{Cursor, Write, Read, Result, Seek, SeekFrom};
+use std::io::{Cursor, Write, Read};
 
 use crate::Codec::CodecFunctions;
-use crate::{DetHashMap, RZ_KEY_TYPE, KEY_LENGTH_BYTES, KEY_LENGTH_BITS};
+use crate::{DetHashMap, RZ_KEY_TYPE};
+
+type HuffmanCodes = DetHashMap<u8, Vec<bool>>;
 
 fn invert_codes(codes: &DetHashMap<u8, Vec<bool>>) -> DetHashMap<Vec<bool>, u8>
 {
@@ -16,12 +17,15 @@ fn invert_codes(codes: &DetHashMap<u8, Vec<bool>>) -> DetHashMap<Vec<bool>, u8>
 fn decode_data_direct(codes: &DetHashMap<u8, Vec<bool>>, encoded_data: &[u8], original_len: usize) -> Vec<u8>
 {
     let inverted: DetHashMap<Vec<bool>, u8> = invert_codes(codes);
+    let longest_code: usize = codes.values().map(Vec::len).max().unwrap_or(0);
     let bits = bits_from_bytes(encoded_data);
-    let mut result: Vec<u8> = Vec::with_capacity(original_len);
+    let mut result: Vec<u8> = Vec::with_capacity(original_len.min(bits.len()));
     let mut buffer: Vec<bool> = Vec::new();
 
     for bit in bits
     {
+        // No code is longer than this, so the data does not match the table
+        if buffer.len() == longest_code { break; }
         buffer.push(bit);
         if let Some(&byte) = inverted.get(&buffer)
         {
@@ -125,7 +129,7 @@ impl PartialOrd for HuffmanTreeItem
 
 impl HuffmanEncoder
 {
-    pub fn obtain_frequencies(vocabulary: &Vec<u8>) ->  DetHashMap<u8, usize>
+    pub fn obtain_frequencies(vocabulary: &[u8]) ->  DetHashMap<u8, usize>
     {
         let mut frequencies: DetHashMap<u8, usize> = DetHashMap::default();
 
@@ -137,7 +141,6 @@ impl HuffmanEncoder
     pub fn new(frequencies: &DetHashMap<u8, usize>) -> Self 
     {
         let mut tree: BinaryHeap<HuffmanTreeItem> = BinaryHeap::<HuffmanTreeItem>::new();
-        let mut nodes: Vec<Box<HuffmanNode>> = Vec::<Box<HuffmanNode>>::new();
 
         // Step 1: Adds leaf nodes into the heap
         for (&byte, &freq) in frequencies.iter() 
@@ -169,10 +172,10 @@ impl HuffmanEncoder
             tree.push(HuffmanTreeItem(internal_freq, internal_node));
         }
         
-        let HuffmanTreeItem(_freq, root_node) = tree.pop().unwrap();
-        let root: Option<Box<HuffmanNode>> = Some(root_node);
+        // Empty input leaves no node, so the tree stays without a root
+        let root: Option<Box<HuffmanNode>> = tree.pop().map(|HuffmanTreeItem(_freq, root_node)| root_node);
         
-        return HuffmanEncoder { root, tree, nodes, };
+        return HuffmanEncoder { root, };
     }
 
     fn generate_codes(&self) -> DetHashMap<u8, Vec<bool>> 
@@ -197,13 +200,18 @@ impl HuffmanEncoder
             }
         }
 
-        if let Some(root) = &self.root { traverse(root, Vec::new(), &mut codes); }
-        else { eprintln!("Error: root does not exist, so codes cannot be generated."); }
+        // A tree without a root has no bytes to code, and a lone leaf still needs a one-bit code to be read back
+        match self.root.as_deref()
+        {
+            Some(root @ HuffmanNode::Leaf { .. }) => { traverse(root, vec![false], &mut codes); }
+            Some(root) => { traverse(root, Vec::new(), &mut codes); }
+            None => {}
+        }
 
         return codes;
     }
 
-    pub fn encode_data(&self, vocabulary: &Vec<u8>) -> (DetHashMap<u8, Vec<bool>>, Vec<u8>) 
+    pub fn encode_data(&self, vocabulary: &[u8]) -> (DetHashMap<u8, Vec<bool>>, Vec<u8>) 
     {
         let codes: DetHashMap<u8, Vec<bool>> = self.generate_codes();
         let mut bit_buffer: Vec<bool> = Vec::new();
@@ -275,7 +283,7 @@ impl HuffmanEncoder
         return Ok(cursor.into_inner());
     }
 
-    pub fn read_from_buffer(buffer: &[u8]) -> std::io::Result<(DetHashMap<u8, Vec<bool>>, Vec<u8>, usize)>
+    pub fn read_from_buffer(buffer: &[u8]) -> std::io::Result<(HuffmanCodes, Vec<u8>, usize)>
     {
         let mut cursor = Cursor::new(buffer);
         
@@ -300,7 +308,7 @@ impl HuffmanEncoder
             let code_len = len_buf[0] as usize;
 
             // It reads bytes, but works with bits, so conversion needed
-            let bytes_needed = (code_len + 7) / 8;
+            let bytes_needed = code_len.div_ceil(8);
             let mut code_bytes = vec![0u8; bytes_needed];
             cursor.read_exact(&mut code_bytes)?; // characters bit-coded
 
@@ -330,13 +338,13 @@ impl HuffmanEncoder
         return Ok((codes, encoded_data, original_len));
     }
 
-    pub fn decode_data(&self, encoded: &Vec<u8>, original_len: usize) -> Vec<u8>
+    pub fn decode_data(&self, encoded: &[u8], original_len: usize) -> Vec<u8>
     {
         let bits: Vec<bool> = bits_from_bytes(encoded);
         let mut result = Vec::with_capacity(original_len);
 
         // 1. Ensures a root node exists, and gets its reference
-        let mut node = self.root.as_ref().expect("No root node");
+        let node = self.root.as_ref().expect("No root node");
         let mut current = node;
 
         // 2. Gets codified bytes
@@ -372,17 +380,17 @@ impl HuffmanEncoder
 
 impl CodecFunctions for HuffmanEncoder
 {
-    fn encode(data: &Vec<u8>, _: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
+    fn encode(data: &[u8], _: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
     {
         let original_len = data.len();
-        let frequencies: DetHashMap<u8, usize> = Self::obtain_frequencies(&data);
-        let mut freqTree: Self = Self::new(&frequencies);
-        let (codes, encoded_data) = freqTree.encode_data(&data);
+        let frequencies: DetHashMap<u8, usize> = Self::obtain_frequencies(data);
+        let freqTree: Self = Self::new(&frequencies);
+        let (codes, encoded_data) = freqTree.encode_data(data);
 
         return Self::write_to_buffer(&codes, &encoded_data, original_len);
     }
 
-    fn decode(encoded_data: &Vec<u8>, _: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
+    fn decode(encoded_data: &[u8], _: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
     {
         match Self::read_from_buffer(encoded_data)
         {
@@ -399,6 +407,20 @@ impl CodecFunctions for HuffmanEncoder
 pub struct HuffmanEncoder
 {
     root: Option<Box<HuffmanNode>>,
-    tree: BinaryHeap<HuffmanTreeItem>,
-    nodes: Vec<Box<HuffmanNode>>,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn empty_and_single_byte_inputs_round_trip()
+    {
+        for data in [Vec::new(), b"aaaa".to_vec()]
+        {
+            let encoded: Vec<u8> = HuffmanEncoder::encode(&data, None).expect("Encoding succeeds");
+            assert_eq!(HuffmanEncoder::decode(&encoded, None).expect("Decoding succeeds"), data);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::env;
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+use zeroize::Zeroizing;
+
+use crate::HashHandling::SecretKey;
+use crate::{RZ_KEY_TYPE, KEY_LENGTH_BYTES};
+
+// Key arguments starting with one of these prefixes are read from somewhere else instead of being the key itself
+const FILE_PREFIX: &str = "file:";
+const ENV_PREFIX: &str = "env:";
+const FD_PREFIX: &str = "fd:";
+const PROMPT: &str = "prompt";
+const LITERAL_PREFIX: &str = "pass:";
+
+pub enum KeyProvider
+{
+    Literal(Zeroizing<String>),
+    File(String),
+    Env(String),
+    Fd(u32),
+    Prompt,
+}
+
+fn strip_line_ending(mut content: Vec<u8>) -> Vec<u8>
+{
+    // Key files and descriptors usually end with a newline that is not part of the key
+    if content.last() == Some(&b'\n') { content.pop(); }
+    if content.last() == Some(&b'\r') { content.pop(); }
+    return content;
+}
+
+impl KeyProvider
+{
+    pub fn from_arg(arg: &str) -> std::io::Result<Self>
+    {
+        if let Some(path) = arg.strip_prefix(FILE_PREFIX) { return Ok(KeyProvider::File(path.to_string())); }
+        if let Some(variable) = arg.strip_prefix(ENV_PREFIX) { return Ok(KeyProvider::Env(variable.to_string())); }
+        if let Some(fd) = arg.strip_prefix(FD_PREFIX)
+        {
+            let fd: u32 = fd.parse().map_err(|_| Error::new(ErrorKind::InvalidInput, format!("Incorrect file descriptor: {}", fd)))?;
+            return Ok(KeyProvider::Fd(fd));
+        }
+        if arg == PROMPT { return Ok(KeyProvider::Prompt); }
+        return Ok(KeyProvider::Literal(Zeroizing::new(arg.strip_prefix(LITERAL_PREFIX).unwrap_or(arg).to_string())));
+    }
+
+    pub fn read(&self, label: &str, confirm: bool) -> std::io::Result<Vec<u8>>
+    {
+        match self
+        {
+            KeyProvider::Literal(key) => { return Ok(key.as_bytes().to_vec()); }
+            KeyProvider::File(path) =>
+            {
+                let content: Vec<u8> = fs::read(path).map_err(|e| Error::new(e.kind(), format!("Key file {} could not be read: {}", path, e)))?;
+                return Ok(strip_line_ending(content));
+            }
+            KeyProvider::Env(variable) =>
+            {
+                let key: Zeroizing<String> = Zeroizing::new(env::var(variable).map_err(|_| Error::new(ErrorKind::NotFound, format!("Environment variable {} is not set", variable)))?);
+                return Ok(key.as_bytes().to_vec());
+            }
+            KeyProvider::Fd(fd) =>
+            {
+                // Reading through /dev/fd avoids taking ownership of a descriptor we did not open
+                #[cfg(unix)]
+                {
+                    let content: Vec<u8> = fs::read(format!("/dev/fd/{}", fd))
+                        .map_err(|e| Error::new(e.kind(), format!("File descriptor {} could not be read: {}", fd, e)))?;
+                    return Ok(strip_line_ending(content));
+                }
+                #[cfg(not(unix))]
+                {
+                    return Err(Error::new(ErrorKind::Unsupported, format!("Reading keys from file descriptor {} is only supported on Unix", fd)));
+                }
+            }
+            KeyProvider::Prompt =>
+            {
+                let key: Zeroizing<String> = Zeroizing::new(rpassword::prompt_password(format!("{}: ", label))?);
+                if confirm && *Zeroizing::new(rpassword::prompt_password(format!("{} (again): ", label))?) != *key
+                {
+                    return Err(Error::new(ErrorKind::InvalidInput, "Keys do not match"));
+                }
+                return Ok(key.as_bytes().to_vec());
+            }
+        }
+    }
+}
+
+pub fn key_from_bytes(key_bytes: &[u8]) -> RZ_KEY_TYPE
+{
+    // Take up to 16 bytes and right-align them in the key (big-endian, as when keys were i128)
+    let mut arr: Zeroizing<[u8; KEY_LENGTH_BYTES]> = Zeroizing::new([0u8; KEY_LENGTH_BYTES]);
+    let n = key_bytes.len().min(KEY_LENGTH_BYTES);
+    arr[KEY_LENGTH_BYTES - n..].copy_from_slice(&key_bytes[..n]);
+    return SecretKey::from_bytes(&arr);
+}
+
+pub fn given_key_bytes(key: &RZ_KEY_TYPE) -> &[u8]
+{
+    // Drops the zero padding added by key_from_bytes, so classical ciphers repeat only the bytes that were given;
+    // leading zero bytes of the key itself cannot be told apart from it, and a key of zeros keeps one
+    let bytes: &[u8; KEY_LENGTH_BYTES] = key.as_bytes();
+    let start: usize = bytes.iter().position(|&byte| byte != 0).unwrap_or(KEY_LENGTH_BYTES - 1);
+    return &bytes[start..];
+}
+
+pub fn resolve_key(arg: &str, label: &str, confirm: bool) -> std::io::Result<RZ_KEY_TYPE>
+{
+    let key_bytes: Zeroizing<Vec<u8>> = Zeroizing::new(KeyProvider::from_arg(arg)?.read(label, confirm)?);
+    if key_bytes.is_empty() { return Err(Error::new(ErrorKind::InvalidInput, format!("{} is empty", label))); }
+    return Ok(key_from_bytes(&key_bytes));
+}
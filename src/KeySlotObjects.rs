@@ -0,0 +1,266 @@
+use aes::Aes128;
+use aes::cipher::{
+    BlockEncrypt, BlockDecrypt,
+    KeyInit,
+    generic_array::GenericArray,
+};
+use x25519_dalek::StaticSecret;
+
+use crate::AESObjects::AESEncoder;
+use crate::Codec::CodecFunctions;
+use crate::KeyHandling::resolve_key;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::HashHandling::{SecretKey, verify_hash, hash_key, random_bytes, derive_password_key};
+use crate::RecipientObjects::{RecipientEncoder, STANZA_LEN};
+use crate::ShareHandling::{read_shares, combine_shares, archive_id_matches};
+use crate::RZ_KEY_TYPE;
+
+const PASSWORD_SLOT: u8 = 0;
+const RECIPIENT_SLOT: u8 = 1;
+
+// Slot specs and credentials prefixed with this are passwords (from any key provider); anything else is a key file
+const PASSWORD_PREFIX: &str = "pw:";
+
+pub enum KeySlot
+{
+    Password { salt: [u8; 16], wrapped_key: [u8; 16] },
+    Recipient { stanza: [u8; STANZA_LEN] },
+}
+
+pub enum Credential
+{
+    Password(RZ_KEY_TYPE),
+    Identity(StaticSecret),
+}
+
+impl Credential
+{
+    pub fn from_spec(spec: &str) -> std::io::Result<Self>
+    {
+        match spec.strip_prefix(PASSWORD_PREFIX)
+        {
+            Some(password) => { return Ok(Credential::Password(resolve_key(password, "Slot password", false)?)); }
+            None => { return Ok(Credential::Identity(RecipientEncoder::load_identity(spec)?)); }
+        }
+    }
+}
+
+impl KeySlot
+{
+    pub fn new(spec: &str, data_key: &RZ_KEY_TYPE) -> std::io::Result<Self>
+    {
+        match spec.strip_prefix(PASSWORD_PREFIX)
+        {
+            Some(password) =>
+            {
+                let password: RZ_KEY_TYPE = resolve_key(password, "New slot password", true)?;
+                let salt: [u8; 16] = random_bytes::<16>()?;
+                let cipher = Aes128::new(GenericArray::from_slice(&*derive_password_key(&password, &salt)));
+                let mut block = GenericArray::clone_from_slice(data_key.as_bytes());
+                cipher.encrypt_block(&mut block);
+
+                let mut wrapped_key: [u8; 16] = [0u8; 16];
+                wrapped_key.copy_from_slice(&block);
+                return Ok(KeySlot::Password { salt, wrapped_key });
+            }
+            None =>
+            {
+                let recipient = RecipientEncoder::load_recipient(spec)?;
+                return Ok(KeySlot::Recipient { stanza: RecipientEncoder::wrap_file_key(data_key, &recipient)? });
+            }
+        }
+    }
+
+    fn unwrap(&self, credential: &Credential) -> Option<RZ_KEY_TYPE>
+    {
+        match (self, credential)
+        {
+            (KeySlot::Password { salt, wrapped_key }, Credential::Password(password)) =>
+            {
+                let cipher = Aes128::new(GenericArray::from_slice(&*derive_password_key(password, salt)));
+                let mut block = GenericArray::clone_from_slice(wrapped_key);
+                cipher.decrypt_block(&mut block);
+
+                let data_key: RZ_KEY_TYPE = SecretKey::from_bytes(block.as_ref());
+                block.zeroize();
+                return Some(data_key);
+            }
+            (KeySlot::Recipient { stanza }, Credential::Identity(identity)) =>
+            {
+                return Some(RecipientEncoder::unwrap_file_key(stanza, identity));
+            }
+            _ => { return None; }
+        }
+    }
+
+    pub fn describe(&self) -> &'static str
+    {
+        match self
+        {
+            KeySlot::Password { .. } => { return "password"; }
+            KeySlot::Recipient { .. } => { return "X25519 recipient"; }
+        }
+    }
+}
+
+impl KeySlotTable
+{
+    pub fn serialize(&self) -> Vec<u8>
+    {
+        // Layout: number of slots (u8), data key validation hash (32 bytes), then every slot as type byte + body
+        let mut buffer: Vec<u8> = Vec::with_capacity(33 + self.slots.len() * (1 + STANZA_LEN));
+        buffer.push(self.slots.len() as u8);
+        buffer.extend_from_slice(&self.key_hash);
+        for slot in &self.slots
+        {
+            match slot
+            {
+                KeySlot::Password { salt, wrapped_key } =>
+                {
+                    buffer.push(PASSWORD_SLOT);
+                    buffer.extend_from_slice(salt);
+                    buffer.extend_from_slice(wrapped_key);
+                }
+                KeySlot::Recipient { stanza } =>
+                {
+                    buffer.push(RECIPIENT_SLOT);
+                    buffer.extend_from_slice(stanza);
+                }
+            }
+        }
+        return buffer;
+    }
+
+    pub fn deserialize(buffer: &[u8]) -> std::io::Result<(Self, usize)>
+    {
+        let too_short = || std::io::Error::new(std::io::ErrorKind::InvalidData, "Key slot table too short");
+
+        let slots_count: usize = *buffer.first().ok_or_else(too_short)? as usize;
+        let mut key_hash: [u8; 32] = [0u8; 32];
+        key_hash.copy_from_slice(buffer.get(1..33).ok_or_else(too_short)?);
+
+        let mut current_byte: usize = 33;
+        let mut slots: Vec<KeySlot> = Vec::with_capacity(slots_count);
+        for _ in 0..slots_count
+        {
+            let slot_type: u8 = *buffer.get(current_byte).ok_or_else(too_short)?;
+            current_byte += 1;
+            match slot_type
+            {
+                PASSWORD_SLOT =>
+                {
+                    let body: &[u8] = buffer.get(current_byte..current_byte + 32).ok_or_else(too_short)?;
+                    let mut salt: [u8; 16] = [0u8; 16];
+                    let mut wrapped_key: [u8; 16] = [0u8; 16];
+                    salt.copy_from_slice(&body[..16]);
+                    wrapped_key.copy_from_slice(&body[16..]);
+                    slots.push(KeySlot::Password { salt, wrapped_key });
+                    current_byte += 32;
+                }
+                RECIPIENT_SLOT =>
+                {
+                    let body: &[u8] = buffer.get(current_byte..current_byte + STANZA_LEN).ok_or_else(too_short)?;
+                    let mut stanza: [u8; STANZA_LEN] = [0u8; STANZA_LEN];
+                    stanza.copy_from_slice(body);
+                    slots.push(KeySlot::Recipient { stanza });
+                    current_byte += STANZA_LEN;
+                }
+                _ => { return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Unknown key slot type: {}", slot_type))); }
+            }
+        }
+
+        return Ok((KeySlotTable { key_hash, slots }, current_byte));
+    }
+
+    pub fn unlock(&self, credentials: &[Credential]) -> std::io::Result<RZ_KEY_TYPE>
+    {
+        for slot in &self.slots
+        {
+            for credential in credentials
+            {
+                if let Some(data_key) = slot.unwrap(credential)
+                    && verify_hash(&data_key, &self.key_hash)
+                {
+                    return Ok(data_key);
+                }
+            }
+        }
+        return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "No credential opens any key slot"));
+    }
+
+    pub fn unlock_with_shares(&self, share_files: &[String]) -> std::io::Result<RZ_KEY_TYPE>
+    {
+        let shares = read_shares(share_files)?;
+        if !shares.iter().all(|share| archive_id_matches(share, &self.key_hash))
+        {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Shares were not split from this file's data key"));
+        }
+
+        let data_key: RZ_KEY_TYPE = combine_shares(&shares)?;
+        if !verify_hash(&data_key, &self.key_hash)
+        {
+            return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "Shares do not reconstruct the data key"));
+        }
+        return Ok(data_key);
+    }
+
+    pub fn add_slot(&mut self, spec: &str, data_key: &RZ_KEY_TYPE) -> std::io::Result<()>
+    {
+        if self.slots.len() >= u8::MAX as usize
+        {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "All 255 key slots are in use"));
+        }
+        self.slots.push(KeySlot::new(spec, data_key)?);
+        return Ok(());
+    }
+
+    pub fn remove_slot(&mut self, index: usize) -> std::io::Result<()>
+    {
+        if index >= self.slots.len()
+        {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Key slot {} does not exist", index)));
+        }
+        if self.slots.len() == 1
+        {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "The last key slot cannot be removed"));
+        }
+        self.slots.remove(index);
+        return Ok(());
+    }
+}
+
+impl KeySlotEncoder
+{
+    pub fn encode(data: &[u8], slot_specs: &[Zeroizing<String>]) -> std::io::Result<(Vec<u8>, KeySlotTable)>
+    {
+        if slot_specs.is_empty() || slot_specs.len() > u8::MAX as usize
+        {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Between 1 and 255 key slots are needed"));
+        }
+
+        // Payload is encrypted once with a random data key; slots only wrap that key, so they can change later
+        let data_key: RZ_KEY_TYPE = SecretKey::random()?;
+        let (key_hash, _) = hash_key(Some(&data_key));
+
+        let mut table: KeySlotTable = KeySlotTable { key_hash, slots: Vec::with_capacity(slot_specs.len()) };
+        for spec in slot_specs { table.add_slot(spec, &data_key)?; }
+
+        return Ok((AESEncoder::encode(data, Some(&data_key))?, table));
+    }
+
+    pub fn decode(encoded_data: &[u8], data_key: &RZ_KEY_TYPE) -> std::io::Result<Vec<u8>>
+    {
+        return AESEncoder::decode(encoded_data, Some(data_key));
+    }
+}
+
+pub struct KeySlotTable
+{
+    pub key_hash: [u8; 32],
+    pub slots: Vec<KeySlot>,
+}
+
+pub struct KeySlotEncoder
+{
+}
@@ -0,0 +1,176 @@
+use std::fs;
+use std::path::Path;
+
+use filetime::FileTime;
+
+use crate::HeaderHandling::{HeaderField, FIELD_OPTIONAL};
+
+// Metadata is optional: older readers simply skip it
+pub const FIELD_FILE_NAME: u8 = FIELD_OPTIONAL | 0x02;
+pub const FIELD_FILE_MODE: u8 = FIELD_OPTIONAL | 0x03;
+pub const FIELD_FILE_MTIME: u8 = FIELD_OPTIONAL | 0x04;
+pub const FIELD_FILE_XATTR: u8 = FIELD_OPTIONAL | 0x05;
+
+// Only permission bits are kept; setuid, setgid and sticky bits are never restored from an archive
+const MODE_MASK: u32 = 0o777;
+
+#[derive(Default)]
+pub struct FileMetadata
+{
+    pub name: Option<String>,
+    pub mode: Option<u32>,
+    pub mtime: Option<(i64, u32)>,
+    pub xattrs: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+fn is_safe_name(name: &str) -> bool
+{
+    // A stored name is only a file name: it may never climb out of the output directory
+    return !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', '\0']);
+}
+
+pub fn format_unix_time(seconds: i64) -> String
+{
+    // Civil date from days since 1970-01-01 (proleptic Gregorian calendar), in UTC
+    let days: i64 = seconds.div_euclid(86_400);
+    let seconds_of_day: i64 = seconds.rem_euclid(86_400);
+    let shifted: i64 = days + 719_468;
+    let era: i64 = shifted.div_euclid(146_097);
+    let day_of_era: i64 = shifted.rem_euclid(146_097);
+    let year_of_era: i64 = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year: i64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index: i64 = (5 * day_of_year + 2) / 153;
+    let day: i64 = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month: i64 = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year: i64 = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    return format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, seconds_of_day / 3600, seconds_of_day / 60 % 60, seconds_of_day % 60);
+}
+
+impl FileMetadata
+{
+    pub fn collect(filepath: &str, with_xattrs: bool) -> std::io::Result<Self>
+    {
+        let file_metadata: fs::Metadata = fs::metadata(filepath)?;
+        let mut metadata: FileMetadata = FileMetadata
+        {
+            name: Path::new(filepath).file_name().and_then(|name| name.to_str()).map(String::from),
+            mtime: { let mtime = FileTime::from_last_modification_time(&file_metadata); Some((mtime.unix_seconds(), mtime.nanoseconds())) },
+            ..FileMetadata::default()
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            use std::os::unix::fs::PermissionsExt;
+
+            metadata.mode = Some(file_metadata.permissions().mode() & MODE_MASK);
+            if with_xattrs
+            {
+                for name in xattr::list(filepath)?
+                {
+                    if let Some(value) = xattr::get(filepath, &name)?
+                    {
+                        // Every attribute must fit in one header field, as name, NUL and value
+                        if name.len() + 1 + value.len() > u16::MAX as usize
+                        {
+                            eprintln!("Extended attribute {} is too large and has not been recorded", name.to_string_lossy());
+                            continue;
+                        }
+                        metadata.xattrs.push((name.as_bytes().to_vec(), value));
+                    }
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = with_xattrs;
+
+        return Ok(metadata);
+    }
+
+    pub fn to_fields(&self) -> Vec<HeaderField>
+    {
+        let mut fields: Vec<HeaderField> = Vec::new();
+        if let Some(name) = &self.name { fields.push(HeaderField { kind: FIELD_FILE_NAME, value: name.as_bytes().to_vec() }); }
+        if let Some(mode) = self.mode { fields.push(HeaderField { kind: FIELD_FILE_MODE, value: mode.to_be_bytes().to_vec() }); }
+        if let Some((seconds, nanoseconds)) = self.mtime
+        {
+            let mut value: Vec<u8> = seconds.to_be_bytes().to_vec();
+            value.extend_from_slice(&nanoseconds.to_be_bytes());
+            fields.push(HeaderField { kind: FIELD_FILE_MTIME, value });
+        }
+        for (name, attribute) in &self.xattrs
+        {
+            let mut value: Vec<u8> = name.clone();
+            value.push(0);
+            value.extend_from_slice(attribute);
+            fields.push(HeaderField { kind: FIELD_FILE_XATTR, value });
+        }
+        return fields;
+    }
+
+    pub fn from_fields(fields: &[HeaderField]) -> Self
+    {
+        // Malformed entries are dropped rather than failing the whole decode
+        let mut metadata: FileMetadata = FileMetadata::default();
+        for field in fields
+        {
+            match field.kind
+            {
+                FIELD_FILE_NAME => { metadata.name = String::from_utf8(field.value.clone()).ok().filter(|name| is_safe_name(name)); }
+                FIELD_FILE_MODE => { metadata.mode = field.value.as_slice().try_into().ok().map(u32::from_be_bytes).map(|mode| mode & MODE_MASK); }
+                FIELD_FILE_MTIME if field.value.len() == 12 =>
+                {
+                    let seconds: i64 = i64::from_be_bytes(field.value[..8].try_into().expect("Seconds take 8 bytes"));
+                    let nanoseconds: u32 = u32::from_be_bytes(field.value[8..].try_into().expect("Nanoseconds take 4 bytes"));
+                    metadata.mtime = Some((seconds, nanoseconds));
+                }
+                FIELD_FILE_XATTR =>
+                {
+                    if let Some(separator) = field.value.iter().position(|&byte| byte == 0)
+                    {
+                        metadata.xattrs.push((field.value[..separator].to_vec(), field.value[separator + 1..].to_vec()));
+                    }
+                }
+                _ => {}
+            }
+        }
+        return metadata;
+    }
+
+    pub fn decoded_path(&self, encoded_path: &str) -> String
+    {
+        // The stored name is restored next to the .rsz file; otherwise .rsz is stripped
+        match &self.name
+        {
+            Some(name) =>
+            {
+                let directory = Path::new(encoded_path).parent().unwrap_or(Path::new(""));
+                return directory.join(name).to_string_lossy().into_owned();
+            }
+            None => { return encoded_path.strip_suffix(".rsz").unwrap_or(encoded_path).to_string(); }
+        }
+    }
+
+    pub fn apply(&self, filepath: &str) -> std::io::Result<()>
+    {
+        #[cfg(unix)]
+        {
+            use std::ffi::OsStr;
+            use std::os::unix::ffi::OsStrExt;
+            use std::os::unix::fs::PermissionsExt;
+
+            for (name, value) in &self.xattrs
+            {
+                if let Err(e) = xattr::set(filepath, OsStr::from_bytes(name), value)
+                {
+                    eprintln!("Extended attribute {} could not be restored: {}", String::from_utf8_lossy(name), e);
+                }
+            }
+            if let Some(mode) = self.mode { fs::set_permissions(filepath, fs::Permissions::from_mode(mode))?; }
+        }
+
+        // Modification time goes last, as writing attributes may touch it
+        if let Some((seconds, nanoseconds)) = self.mtime { filetime::set_file_mtime(filepath, FileTime::from_unix_time(seconds, nanoseconds))?; }
+        return Ok(());
+    }
+}
@@ -1,5 +1,13 @@
 use crate::Codec::CodecFunctions;
-use crate::{RZ_KEY_TYPE, KEY_LENGTH_BYTES, KEY_LENGTH_BITS};
+use crate::RZ_KEY_TYPE;
+
+impl Default for RLEEncoder
+{
+    fn default() -> Self
+    {
+        return Self::new();
+    }
+}
 
 impl RLEEncoder
 {
@@ -28,10 +36,16 @@ impl RLEEncoder
         return RLEEncoder{ dictionary: list, };
     }
 
+    pub fn expanded_len(encoded_data: &[u8]) -> u64
+    {
+        // Size decoding would produce, computed without expanding anything
+        return encoded_data.chunks_exact(5).map(|entry| u32::from_be_bytes([entry[1], entry[2], entry[3], entry[4]]) as u64).sum();
+    }
+
     fn expand_rle(&self) -> Vec<u8> 
     {
         let mut result = Vec::new();
-        for (byte, count) in &self.dictionary { result.extend(std::iter::repeat(*byte).take(*count as usize)); }
+        for (byte, count) in &self.dictionary { result.extend(std::iter::repeat_n(*byte, *count as usize)); }
         return result;
     }
 
@@ -63,13 +77,13 @@ impl RLEEncoder
 
 impl CodecFunctions for RLEEncoder
 {
-    fn encode(data: &Vec<u8>, _: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
+    fn encode(data: &[u8], _: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
     {
-        let mut rle: RLEEncoder = RLEEncoder::new_from_buffer(data);
+        let rle: RLEEncoder = RLEEncoder::new_from_buffer(data);
         return Ok(rle.serialize_rle());
     }
 
-    fn decode(encoded_data: &Vec<u8>, _: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
+    fn decode(encoded_data: &[u8], _: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
     {
         let mut rle: RLEEncoder = RLEEncoder::new();
         rle.deserialize_rle(encoded_data);
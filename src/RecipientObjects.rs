@@ -0,0 +1,142 @@
+use aes::Aes128;
+use aes::cipher::{
+    BlockEncrypt, BlockDecrypt,
+    KeyInit,
+    generic_array::GenericArray,
+};
+use sha2::{Sha256, Digest};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::AESObjects::AESEncoder;
+use crate::Codec::CodecFunctions;
+use crate::EnvHandling::{read_key_file, write_key_file};
+use zeroize::Zeroize;
+
+use crate::HashHandling::{SecretKey, verify_hash, random_bytes};
+use crate::RZ_KEY_TYPE;
+
+pub const PUBLIC_KEY_LABEL: &str = "rsz-x25519-public";
+pub const PRIVATE_KEY_LABEL: &str = "rsz-x25519-private";
+
+// Ephemeral public key (32 bytes) + wrapped file key (16 bytes)
+pub const STANZA_LEN: usize = 32 + 16;
+
+fn derive_wrapping_key(shared_secret: &[u8; 32], ephemeral: &PublicKey, recipient: &PublicKey) -> Aes128
+{
+    // Binds the wrapping key to both public keys, so a stanza cannot be replayed for another recipient
+    let mut hasher = Sha256::new();
+    hasher.update(b"RustyZipper-X25519");
+    hasher.update(shared_secret);
+    hasher.update(ephemeral.as_bytes());
+    hasher.update(recipient.as_bytes());
+    let digest = hasher.finalize();
+    return Aes128::new(GenericArray::from_slice(&digest[..16]));
+}
+
+impl RecipientEncoder
+{
+    pub fn generate_keypair(name: &str) -> std::io::Result<(String, String)>
+    {
+        let secret: StaticSecret = StaticSecret::from(random_bytes::<32>()?);
+        let public: PublicKey = PublicKey::from(&secret);
+
+        let public_path = format!("{}.pub", name);
+        let private_path = format!("{}.key", name);
+        write_key_file(&private_path, PRIVATE_KEY_LABEL, secret.as_bytes(), true)?;
+        write_key_file(&public_path, PUBLIC_KEY_LABEL, public.as_bytes(), false)?;
+        return Ok((public_path, private_path));
+    }
+
+    pub fn load_recipient(filename: &str) -> std::io::Result<PublicKey>
+    {
+        let key_bytes: [u8; 32] = read_key_file(filename, PUBLIC_KEY_LABEL)?.try_into()
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "Public key must be 32 bytes long"))?;
+        return Ok(PublicKey::from(key_bytes));
+    }
+
+    pub fn load_identity(filename: &str) -> std::io::Result<StaticSecret>
+    {
+        let key_bytes: [u8; 32] = read_key_file(filename, PRIVATE_KEY_LABEL)?.try_into()
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "Private key must be 32 bytes long"))?;
+        return Ok(StaticSecret::from(key_bytes));
+    }
+
+    pub fn wrap_file_key(file_key: &RZ_KEY_TYPE, recipient: &PublicKey) -> std::io::Result<[u8; STANZA_LEN]>
+    {
+        let ephemeral_secret: StaticSecret = StaticSecret::from(random_bytes::<32>()?);
+        let ephemeral_public: PublicKey = PublicKey::from(&ephemeral_secret);
+        let shared_secret = ephemeral_secret.diffie_hellman(recipient);
+
+        let cipher: Aes128 = derive_wrapping_key(shared_secret.as_bytes(), &ephemeral_public, recipient);
+        let mut block = GenericArray::clone_from_slice(file_key.as_bytes());
+        cipher.encrypt_block(&mut block);
+
+        let mut stanza: [u8; STANZA_LEN] = [0u8; STANZA_LEN];
+        stanza[..32].copy_from_slice(ephemeral_public.as_bytes());
+        stanza[32..].copy_from_slice(&block);
+        return Ok(stanza);
+    }
+
+    pub fn unwrap_file_key(stanza: &[u8], identity: &StaticSecret) -> RZ_KEY_TYPE
+    {
+        let ephemeral_bytes: [u8; 32] = stanza[..32].try_into().expect("Stanza too short");
+        let ephemeral_public: PublicKey = PublicKey::from(ephemeral_bytes);
+        let recipient: PublicKey = PublicKey::from(identity);
+        let shared_secret = identity.diffie_hellman(&ephemeral_public);
+
+        let cipher: Aes128 = derive_wrapping_key(shared_secret.as_bytes(), &ephemeral_public, &recipient);
+        let mut block = GenericArray::clone_from_slice(&stanza[32..STANZA_LEN]);
+        cipher.decrypt_block(&mut block);
+
+        // A wrong identity yields a random-looking key, which is later rejected by the AES validation hash
+        let file_key: RZ_KEY_TYPE = SecretKey::from_bytes(block.as_ref());
+        block.zeroize();
+        return file_key;
+    }
+
+    pub fn encode(data: &[u8], recipients: &[PublicKey]) -> std::io::Result<Vec<u8>>
+    {
+        if recipients.is_empty() || recipients.len() > u8::MAX as usize
+        {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Between 1 and 255 recipients are needed"));
+        }
+
+        // Payload is encrypted once with a random file key, which is then wrapped for every recipient
+        let file_key: RZ_KEY_TYPE = SecretKey::random()?;
+
+        let mut result: Vec<u8> = Vec::with_capacity(1 + recipients.len() * STANZA_LEN + data.len() + 48);
+        result.push(recipients.len() as u8);
+        for recipient in recipients { result.extend_from_slice(&Self::wrap_file_key(&file_key, recipient)?); }
+        result.extend_from_slice(&AESEncoder::encode(data, Some(&file_key))?);
+        return Ok(result);
+    }
+
+    pub fn decode(encoded_data: &[u8], identities: &[StaticSecret]) -> std::io::Result<Vec<u8>>
+    {
+        let recipients_count: usize = *encoded_data.first()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "Encoded data too short"))? as usize;
+        let stanzas_end: usize = 1 + recipients_count * STANZA_LEN;
+        if encoded_data.len() < stanzas_end + 32
+        {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Encoded data too short"));
+        }
+
+        let aes_payload: &[u8] = &encoded_data[stanzas_end..];
+        let validation_hash: &[u8] = &aes_payload[..32];
+
+        for stanza in encoded_data[1..stanzas_end].chunks(STANZA_LEN)
+        {
+            for identity in identities
+            {
+                let file_key: RZ_KEY_TYPE = Self::unwrap_file_key(stanza, identity);
+                if verify_hash(&file_key, validation_hash) { return AESEncoder::decode(aes_payload, Some(&file_key)); }
+            }
+        }
+
+        return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "No identity matches any recipient of this file"));
+    }
+}
+
+pub struct RecipientEncoder
+{
+}
@@ -0,0 +1,227 @@
+use std::io::{Error, ErrorKind};
+
+use reed_solomon_erasure::galois_8::ReedSolomon;
+
+// Recovery record appended to the container: a copy of the footer, parity shards, CRC32 of every data and parity shard, then the footer
+// Footer: container length (u64), shard size (u32), data shards (u8), parity shards (u8), redundancy (u8), CRC32 of these fields, magic
+// The copy right after the container is found by searching when the footer at the end is damaged, so the parity is never lost with it
+const RECOVERY_MAGIC: &[u8; 8] = b"RSZREC01";
+pub const RECOVERY_FOOTER_LEN: usize = 8 + 4 + 1 + 1 + 1 + 4 + 8;
+// GF(256) allows 256 shards in total; shards below this size are not worth their checksum
+const MAX_SHARDS: usize = 256;
+const MIN_SHARD_SIZE: usize = 64;
+
+pub struct RecoveryRecord
+{
+    pub container_len: usize,
+    pub shard_size: usize,
+    pub data_shards: usize,
+    pub parity_shards: usize,
+    pub redundancy: u8,
+    // Set when either footer is damaged; the record is still read from the other one
+    pub footer_damaged: bool,
+}
+
+fn codec_error(e: reed_solomon_erasure::Error) -> Error
+{
+    return Error::new(ErrorKind::InvalidData, format!("Reed-Solomon error: {:?}", e));
+}
+
+impl RecoveryRecord
+{
+    fn for_container(container_len: usize, redundancy: u8) -> Self
+    {
+        // As many data shards as possible, leaving room for the parity ones within the GF(256) limit
+        let mut data_shards: usize = container_len.div_ceil(MIN_SHARD_SIZE).clamp(1, MAX_SHARDS - 1);
+        let mut parity_shards: usize = (data_shards * redundancy as usize).div_ceil(100).max(1);
+        while data_shards + parity_shards > MAX_SHARDS
+        {
+            data_shards -= 1;
+            parity_shards = (data_shards * redundancy as usize).div_ceil(100).max(1);
+        }
+        let shard_size: usize = container_len.div_ceil(data_shards).max(1);
+        return RecoveryRecord { container_len, shard_size, data_shards, parity_shards, redundancy, footer_damaged: false };
+    }
+
+    fn footer(&self) -> Vec<u8>
+    {
+        let mut footer: Vec<u8> = Vec::with_capacity(RECOVERY_FOOTER_LEN);
+        footer.extend_from_slice(&(self.container_len as u64).to_be_bytes());
+        footer.extend_from_slice(&(self.shard_size as u32).to_be_bytes());
+        footer.push(self.data_shards as u8);
+        footer.push(self.parity_shards as u8);
+        footer.push(self.redundancy);
+        footer.extend_from_slice(&crc32fast::hash(&footer).to_be_bytes());
+        footer.extend_from_slice(RECOVERY_MAGIC);
+        return footer;
+    }
+
+    pub fn from_footer(footer: &[u8]) -> std::io::Result<Option<Self>>
+    {
+        // Files without the magic carry no recovery record
+        if footer.len() != RECOVERY_FOOTER_LEN || !footer.ends_with(RECOVERY_MAGIC) { return Ok(None); }
+        let fields: &[u8] = &footer[..15];
+        if crc32fast::hash(fields).to_be_bytes() != footer[15..19]
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "Recovery record footer is damaged; the file cannot be repaired"));
+        }
+        let record: RecoveryRecord = RecoveryRecord
+        {
+            container_len: u64::from_be_bytes(fields[..8].try_into().expect("Container length takes 8 bytes")) as usize,
+            shard_size: u32::from_be_bytes(fields[8..12].try_into().expect("Shard size takes 4 bytes")) as usize,
+            data_shards: fields[12] as usize,
+            parity_shards: fields[13] as usize,
+            redundancy: fields[14],
+            footer_damaged: false,
+        };
+        if record.data_shards == 0 || record.parity_shards == 0 || record.data_shards + record.parity_shards > MAX_SHARDS
+            || record.shard_size.checked_mul(record.data_shards).is_none_or(|data_len| data_len < record.container_len)
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "Recovery record footer is malformed"));
+        }
+        return Ok(Some(record));
+    }
+
+    fn find_footer_copy(buffer: &[u8]) -> Option<Self>
+    {
+        // Searched from the end, for a valid footer sitting right after the container it describes
+        let mut search_end: usize = buffer.len();
+        while let Some(magic_start) = buffer[..search_end].windows(RECOVERY_MAGIC.len()).rposition(|window| window == RECOVERY_MAGIC)
+        {
+            let footer_end: usize = magic_start + RECOVERY_MAGIC.len();
+            if let Some(footer_start) = footer_end.checked_sub(RECOVERY_FOOTER_LEN)
+                && let Ok(Some(mut record)) = RecoveryRecord::from_footer(&buffer[footer_start..footer_end])
+                && record.container_len == footer_start && footer_start + record.record_len() <= buffer.len()
+            {
+                record.footer_damaged = true;
+                return Some(record);
+            }
+            search_end = footer_end - 1;
+        }
+        return None;
+    }
+
+    fn parity_start(&self) -> usize
+    {
+        return self.container_len + RECOVERY_FOOTER_LEN;
+    }
+
+    fn shards_count(&self) -> usize
+    {
+        return self.data_shards + self.parity_shards;
+    }
+
+    pub fn record_len(&self) -> usize
+    {
+        // Everything after the container: footer copy, parity shards, checksum table and footer
+        return RECOVERY_FOOTER_LEN + self.parity_shards * self.shard_size + self.shards_count() * 4 + RECOVERY_FOOTER_LEN;
+    }
+
+    fn shards(&self, buffer: &[u8]) -> Vec<Vec<u8>>
+    {
+        // Data shards are cut from the container, the last one padded with zeros; parity shards follow it
+        let container: &[u8] = &buffer[..self.container_len];
+        let mut shards: Vec<Vec<u8>> = Vec::with_capacity(self.shards_count());
+        for i_shard in 0..self.data_shards
+        {
+            let start: usize = (i_shard * self.shard_size).min(self.container_len);
+            let mut shard: Vec<u8> = container[start..(start + self.shard_size).min(self.container_len)].to_vec();
+            shard.resize(self.shard_size, 0);
+            shards.push(shard);
+        }
+        // A bare container, as when the record is first computed, has no parity shards yet
+        let parity: &[u8] = buffer.get(self.parity_start()..).unwrap_or_default();
+        for parity_shard in parity.chunks_exact(self.shard_size).take(self.parity_shards)
+        {
+            shards.push(parity_shard.to_vec());
+        }
+        return shards;
+    }
+
+    pub fn damaged_shards(&self, buffer: &[u8]) -> Vec<usize>
+    {
+        // A damaged checksum entry marks its shard as damaged too, which only costs one parity shard
+        let table_start: usize = self.parity_start() + self.parity_shards * self.shard_size;
+        let table: &[u8] = &buffer[table_start..table_start + self.shards_count() * 4];
+        return self.shards(buffer).iter().zip(table.chunks_exact(4)).enumerate()
+            .filter(|(_, (shard, stored_crc))| crc32fast::hash(shard).to_be_bytes() != **stored_crc)
+            .map(|(i_shard, _)| i_shard)
+            .collect();
+    }
+
+    pub fn repair(&self, buffer: &[u8], damaged: &[usize]) -> std::io::Result<Vec<u8>>
+    {
+        if damaged.len() > self.parity_shards
+        {
+            return Err(Error::new(ErrorKind::InvalidData, format!("{} shards are damaged, but at most {} can be rebuilt", damaged.len(), self.parity_shards)));
+        }
+        let mut shards: Vec<Option<Vec<u8>>> = self.shards(buffer).into_iter().enumerate()
+            .map(|(i_shard, shard)| if damaged.contains(&i_shard) { None } else { Some(shard) })
+            .collect();
+        ReedSolomon::new(self.data_shards, self.parity_shards).map_err(codec_error)?
+            .reconstruct_data(&mut shards).map_err(codec_error)?;
+
+        let mut container: Vec<u8> = shards.into_iter().take(self.data_shards).flatten().flatten().collect();
+        container.truncate(self.container_len);
+        return Ok(container);
+    }
+
+    pub fn describe(&self) -> String
+    {
+        return format!("{}% ({} data and {} parity shards of {} bytes)", self.redundancy, self.data_shards, self.parity_shards, self.shard_size);
+    }
+}
+
+pub fn add_recovery_record(container: &[u8], redundancy: u8) -> std::io::Result<Vec<u8>>
+{
+    let record: RecoveryRecord = RecoveryRecord::for_container(container.len(), redundancy);
+    let mut shards: Vec<Vec<u8>> = record.shards(container);
+    shards.resize(record.shards_count(), vec![0u8; record.shard_size]);
+    ReedSolomon::new(record.data_shards, record.parity_shards).map_err(codec_error)?
+        .encode(&mut shards).map_err(codec_error)?;
+
+    let mut content: Vec<u8> = Vec::with_capacity(container.len() + record.record_len());
+    content.extend_from_slice(container);
+    content.extend_from_slice(&record.footer());
+    for parity_shard in &shards[record.data_shards..] { content.extend_from_slice(parity_shard); }
+    for shard in &shards { content.extend_from_slice(&crc32fast::hash(shard).to_be_bytes()); }
+    content.extend_from_slice(&record.footer());
+    return Ok(content);
+}
+
+pub fn split_recovery_record(buffer: &[u8]) -> std::io::Result<(&[u8], Option<RecoveryRecord>)>
+{
+    if buffer.len() < RECOVERY_FOOTER_LEN { return Ok((buffer, None)); }
+    let mut record: RecoveryRecord = match RecoveryRecord::from_footer(&buffer[buffer.len() - RECOVERY_FOOTER_LEN..])
+    {
+        Ok(Some(record)) => record,
+        // A damaged or missing footer may still have its copy; the file may also have had no record at all
+        footer => match RecoveryRecord::find_footer_copy(buffer)
+        {
+            Some(record) => { return Ok((&buffer[..record.container_len], Some(record))); }
+            None => { return footer.map(|_| (buffer, None)); }
+        },
+    };
+    if record.container_len.checked_add(record.record_len()) != Some(buffer.len())
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Recovery record does not match the file length: the file is truncated or extended"));
+    }
+    record.footer_damaged = buffer[record.container_len..record.parity_start()] != buffer[buffer.len() - RECOVERY_FOOTER_LEN..];
+    return Ok((&buffer[..record.container_len], Some(record)));
+}
+
+pub fn check_recovery_record(buffer: &[u8]) -> std::io::Result<(&[u8], Option<RecoveryRecord>)>
+{
+    // Damaged files are refused, pointing to the repair mode, rather than decoded into garbage
+    let (container, record) = split_recovery_record(buffer)?;
+    if let Some(record) = &record
+    {
+        if record.footer_damaged { eprintln!("Warning: one of the two recovery record footers is damaged; run `RustyZipper repair <file>` to rewrite it."); }
+        let damaged: Vec<usize> = record.damaged_shards(buffer);
+        if !damaged.is_empty()
+        {
+            return Err(Error::new(ErrorKind::InvalidData, format!("{} of {} shards are damaged; run `RustyZipper repair <file>` to rebuild them before decoding", damaged.len(), record.shards_count())));
+        }
+    }
+    return Ok((container, record));
+}
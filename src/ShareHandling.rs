@@ -0,0 +1,133 @@
+use std::io::{Error, ErrorKind};
+
+use zeroize::Zeroizing;
+
+use crate::EnvHandling::{read_key_file, write_key_file};
+use crate::HashHandling::{SecretKey, random_bytes};
+use crate::{RZ_KEY_TYPE, KEY_LENGTH_BYTES};
+
+pub const SHARE_LABEL: &str = "rsz-shamir-share";
+
+// Share body: threshold (u8), share index (u8), archive id (first 8 bytes of the key validation hash), then one byte per key byte
+const ARCHIVE_ID_LEN: usize = 8;
+const SHARE_LEN: usize = 2 + ARCHIVE_ID_LEN + KEY_LENGTH_BYTES;
+
+// Arithmetic in GF(2^8) with the AES polynomial x^8 + x^4 + x^3 + x + 1
+fn gf_mul(mut a: u8, mut b: u8) -> u8
+{
+    let mut product: u8 = 0;
+    while b != 0
+    {
+        if b & 1 != 0 { product ^= a; }
+        let carry: bool = a & 0x80 != 0;
+        a <<= 1;
+        if carry { a ^= 0x1b; }
+        b >>= 1;
+    }
+    return product;
+}
+
+fn gf_inv(a: u8) -> u8
+{
+    // a^254 is the inverse of any non-zero element, since a^255 = 1
+    let mut result: u8 = 1;
+    for _ in 0..254 { result = gf_mul(result, a); }
+    return result;
+}
+
+pub fn split_key(key: &RZ_KEY_TYPE, key_hash: &[u8; 32], threshold: u8, count: u8) -> std::io::Result<Vec<Zeroizing<Vec<u8>>>>
+{
+    if threshold == 0 || threshold > count
+    {
+        return Err(Error::new(ErrorKind::InvalidInput, "Threshold must be between 1 and the number of shares"));
+    }
+
+    // Every key byte is the constant term of its own random polynomial of degree threshold - 1
+    let mut coefficients: Zeroizing<Vec<[u8; KEY_LENGTH_BYTES]>> = Zeroizing::new(vec![*key.as_bytes()]);
+    for _ in 1..threshold { coefficients.push(random_bytes::<KEY_LENGTH_BYTES>()?); }
+
+    let mut shares: Vec<Zeroizing<Vec<u8>>> = Vec::with_capacity(count as usize);
+    for x in 1..=count
+    {
+        let mut share: Zeroizing<Vec<u8>> = Zeroizing::new(Vec::with_capacity(SHARE_LEN));
+        share.push(threshold);
+        share.push(x);
+        share.extend_from_slice(&key_hash[..ARCHIVE_ID_LEN]);
+        for i_byte in 0..KEY_LENGTH_BYTES
+        {
+            // Horner's rule, from the highest degree coefficient down
+            let y: u8 = coefficients.iter().rev().fold(0u8, |acc, coefficient| gf_mul(acc, x) ^ coefficient[i_byte]);
+            share.push(y);
+        }
+        shares.push(share);
+    }
+    return Ok(shares);
+}
+
+pub fn combine_shares(shares: &[Zeroizing<Vec<u8>>]) -> std::io::Result<RZ_KEY_TYPE>
+{
+    let first: &[u8] = shares.first().ok_or_else(|| Error::new(ErrorKind::InvalidInput, "No shares given"))?;
+    let threshold: usize = first[0] as usize;
+    if shares.iter().any(|share| share[0] != first[0] || share[2..2 + ARCHIVE_ID_LEN] != first[2..2 + ARCHIVE_ID_LEN])
+    {
+        return Err(Error::new(ErrorKind::InvalidInput, "Shares belong to different archives or splits"));
+    }
+
+    let mut xs: Vec<u8> = shares.iter().map(|share| share[1]).collect();
+    xs.sort_unstable();
+    xs.dedup();
+    if xs.len() != shares.len() { return Err(Error::new(ErrorKind::InvalidInput, "The same share was given twice")); }
+    if shares.len() < threshold
+    {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("{} shares are needed, but only {} were given", threshold, shares.len())));
+    }
+
+    // Lagrange interpolation at x = 0 over the first threshold shares; subtraction is XOR in GF(2^8)
+    let used: &[Zeroizing<Vec<u8>>] = &shares[..threshold];
+    let mut key_bytes: Zeroizing<[u8; KEY_LENGTH_BYTES]> = Zeroizing::new([0u8; KEY_LENGTH_BYTES]);
+    for (i, share) in used.iter().enumerate()
+    {
+        let mut basis: u8 = 1;
+        for (j, other) in used.iter().enumerate()
+        {
+            if i != j { basis = gf_mul(basis, gf_mul(other[1], gf_inv(other[1] ^ share[1]))); }
+        }
+        for (i_byte, key_byte) in key_bytes.iter_mut().enumerate()
+        {
+            *key_byte ^= gf_mul(basis, share[2 + ARCHIVE_ID_LEN + i_byte]);
+        }
+    }
+    return Ok(SecretKey::from_bytes(&key_bytes));
+}
+
+pub fn write_shares(filepath: &str, key: &RZ_KEY_TYPE, key_hash: &[u8; 32], threshold: u8, count: u8) -> std::io::Result<Vec<String>>
+{
+    let mut share_paths: Vec<String> = Vec::with_capacity(count as usize);
+    for share in split_key(key, key_hash, threshold, count)?
+    {
+        let share_path: String = format!("{}.share{}", filepath, share[1]);
+        write_key_file(&share_path, SHARE_LABEL, &share, true)?;
+        share_paths.push(share_path);
+    }
+    return Ok(share_paths);
+}
+
+pub fn read_shares(share_files: &[String]) -> std::io::Result<Vec<Zeroizing<Vec<u8>>>>
+{
+    let mut shares: Vec<Zeroizing<Vec<u8>>> = Vec::with_capacity(share_files.len());
+    for share_file in share_files
+    {
+        let share: Zeroizing<Vec<u8>> = Zeroizing::new(read_key_file(share_file, SHARE_LABEL)?);
+        if share.len() != SHARE_LEN || share[1] == 0
+        {
+            return Err(Error::new(ErrorKind::InvalidData, format!("{} is not a valid share", share_file)));
+        }
+        shares.push(share);
+    }
+    return Ok(shares);
+}
+
+pub fn archive_id_matches(share: &[u8], key_hash: &[u8; 32]) -> bool
+{
+    return share[2..2 + ARCHIVE_ID_LEN] == key_hash[..ARCHIVE_ID_LEN];
+}
@@ -0,0 +1,135 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+use crate::EnvHandling::{read_key_file, write_key_file, to_hex, from_hex, replace_file_atomically};
+use crate::HashHandling::random_bytes;
+
+pub const PUBLIC_KEY_LABEL: &str = "rsz-ed25519-public";
+pub const PRIVATE_KEY_LABEL: &str = "rsz-ed25519-private";
+pub const SIGNATURE_LABEL: &str = "rsz-ed25519-signature";
+
+// Embedded trailer: signature (64 bytes) + signer public key (32 bytes) + magic (8 bytes)
+pub const TRAILER_MAGIC: &[u8; 8] = b"RSZSIG01";
+pub const TRAILER_LEN: usize = 64 + 32 + 8;
+
+pub fn generate_signing_keypair(name: &str) -> std::io::Result<(String, String)>
+{
+    let signing_key: SigningKey = SigningKey::from_bytes(&random_bytes::<32>()?);
+
+    let public_path = format!("{}.pub", name);
+    let private_path = format!("{}.key", name);
+    write_key_file(&private_path, PRIVATE_KEY_LABEL, signing_key.as_bytes(), true)?;
+    write_key_file(&public_path, PUBLIC_KEY_LABEL, signing_key.verifying_key().as_bytes(), false)?;
+    return Ok((public_path, private_path));
+}
+
+fn load_signing_key(filename: &str) -> std::io::Result<SigningKey>
+{
+    let key_bytes: [u8; 32] = read_key_file(filename, PRIVATE_KEY_LABEL)?.try_into()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "Private key must be 32 bytes long"))?;
+    return Ok(SigningKey::from_bytes(&key_bytes));
+}
+
+pub fn load_verifying_key(filename: &str) -> std::io::Result<VerifyingKey>
+{
+    let key_bytes: [u8; 32] = read_key_file(filename, PUBLIC_KEY_LABEL)?.try_into()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "Public key must be 32 bytes long"))?;
+    return VerifyingKey::from_bytes(&key_bytes)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "Invalid Ed25519 public key"));
+}
+
+fn detached_signature_path(filename: &str) -> String
+{
+    return format!("{}.sig", filename);
+}
+
+pub fn split_signature_trailer(buffer: &[u8]) -> (&[u8], Option<(Signature, [u8; 32])>)
+{
+    if buffer.len() < TRAILER_LEN || !buffer.ends_with(TRAILER_MAGIC) { return (buffer, None); }
+
+    let trailer: &[u8] = &buffer[buffer.len() - TRAILER_LEN..];
+    let signature: Signature = Signature::from_bytes(trailer[..64].try_into().expect("Trailer holds a 64 byte signature"));
+    let signer: [u8; 32] = trailer[64..96].try_into().expect("Trailer holds a 32 byte public key");
+    return (&buffer[..buffer.len() - TRAILER_LEN], Some((signature, signer)));
+}
+
+pub fn sign_file(filename: &str, private_key_file: &str, detached: bool) -> std::io::Result<()>
+{
+    let signing_key: SigningKey = load_signing_key(private_key_file)?;
+    let buffer: Vec<u8> = fs::read(filename)?;
+
+    // Signs the whole container (header, codec list and payload), replacing any previous embedded signature
+    let (container, _) = split_signature_trailer(&buffer);
+    let signature: Signature = signing_key.sign(container);
+
+    if detached
+    {
+        let mut content: Vec<u8> = Vec::new();
+        content.extend_from_slice(format!("{}:{}\n", SIGNATURE_LABEL, to_hex(&signature.to_bytes())).as_bytes());
+        return replace_file_atomically(&detached_signature_path(filename), &content);
+    }
+
+    let mut signed: Vec<u8> = Vec::with_capacity(container.len() + TRAILER_LEN);
+    signed.extend_from_slice(container);
+    signed.extend_from_slice(&signature.to_bytes());
+    signed.extend_from_slice(signing_key.verifying_key().as_bytes());
+    signed.extend_from_slice(TRAILER_MAGIC);
+    return replace_file_atomically(filename, &signed);
+}
+
+pub fn verify_file(filename: &str, public_key_file: &str) -> std::io::Result<()>
+{
+    let verifying_key: VerifyingKey = load_verifying_key(public_key_file)?;
+    let buffer: Vec<u8> = fs::read(filename)?;
+
+    let (container, embedded) = split_signature_trailer(&buffer);
+    let signature: Signature = match embedded
+    {
+        Some((signature, _)) => signature,
+        None =>
+        {
+            let detached_path: String = detached_signature_path(filename);
+            let content: String = fs::read_to_string(&detached_path)
+                .map_err(|e| Error::new(e.kind(), format!("File has no embedded signature and {} could not be read: {}", detached_path, e)))?;
+            let signature_bytes: [u8; 64] = match content.trim().split_once(':')
+            {
+                Some((SIGNATURE_LABEL, hex)) => from_hex(hex)?.try_into()
+                    .map_err(|_| Error::new(ErrorKind::InvalidData, "Signature must be 64 bytes long"))?,
+                _ => { return Err(Error::new(ErrorKind::InvalidData, format!("{} is not a signature file", detached_path))); }
+            };
+            Signature::from_bytes(&signature_bytes)
+        }
+    };
+
+    return verifying_key.verify_strict(container, &signature)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "Signature does not match: file has been tampered with or was signed by another key"));
+}
+
+pub fn check_embedded_signature<'a>(buffer: &'a [u8], trusted_signer: Option<&VerifyingKey>) -> std::io::Result<&'a [u8]>
+{
+    // Files carrying a signature trailer are checked before any decoding starts. The trailer names its own signer,
+    // so it only proves authorship when that signer is the trusted key; anyone can re-sign a modified file with their own key
+    let (container, embedded) = split_signature_trailer(buffer);
+    let Some((signature, signer)) = embedded else
+    {
+        if trusted_signer.is_some() { return Err(Error::new(ErrorKind::InvalidData, "File carries no embedded signature, but a trusted signer was required")); }
+        return Ok(container);
+    };
+
+    let verifying_key: VerifyingKey = VerifyingKey::from_bytes(&signer)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "Embedded signer key is invalid"))?;
+    verifying_key.verify_strict(container, &signature)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "Embedded signature does not match: file has been tampered with"))?;
+    match trusted_signer
+    {
+        Some(trusted_key) if trusted_key.as_bytes() != &signer =>
+        {
+            return Err(Error::new(ErrorKind::InvalidData, format!("File was signed by {}, not by the trusted key {}", to_hex(&signer), to_hex(trusted_key.as_bytes()))));
+        }
+        Some(_) => { eprintln!("Embedded signature is valid and made by the trusted key {}.", to_hex(&signer)); }
+        None => { eprintln!("Embedded signature matches the content, but its signer {} is unverified; use --verify-key or verify to check who signed it.", to_hex(&signer)); }
+    }
+    return Ok(container);
+}
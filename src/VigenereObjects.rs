@@ -0,0 +1,71 @@
+use crate::Codec::CodecFunctions;
+
+use crate::HashHandling::{verify_hash, hash_key};
+use crate::KeyHandling::given_key_bytes;
+use crate::RZ_KEY_TYPE;
+
+impl CodecFunctions for VigenereEncoder
+{
+    fn encode(data: &[u8], key: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
+    {
+        let mut result: Vec<u8> = Vec::<u8>::with_capacity(data.len() + 32);
+
+        let (key_hashed, key_ref): ([u8; 32], &RZ_KEY_TYPE) = hash_key(key);
+        let key_bytes: &[u8] = given_key_bytes(key_ref);
+
+        // Every byte is shifted by the key byte at its position, repeating the key as it was given
+        result.extend_from_slice(&key_hashed);
+        for (i, &byte) in data.iter().enumerate()
+        {
+            result.push(byte.wrapping_add(key_bytes[i % key_bytes.len()]));
+        }
+        return Ok(result);
+    }
+
+    fn decode(encoded_data: &[u8], possible_key: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
+    { 
+        if encoded_data.len() < 32 { return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Encoded data too short")); }
+
+        let possible_key_ref: &RZ_KEY_TYPE = possible_key.expect("Error: No key");
+        let hashed_key: &[u8] = &encoded_data[..32];
+        
+        if !verify_hash(possible_key_ref, hashed_key) { return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "Invalid key")); }
+
+        let key_bytes: &[u8] = given_key_bytes(possible_key_ref);
+        let mut result: Vec<u8> = Vec::with_capacity(encoded_data.len() - 32);
+        for (i, &byte) in encoded_data.iter().skip(32).enumerate() { result.push(byte.wrapping_sub(key_bytes[i % key_bytes.len()])); }
+        return Ok(result);
+    }
+}
+
+pub struct VigenereEncoder
+{
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::KeyHandling::key_from_bytes;
+
+    #[test]
+    fn short_key_leaves_no_plaintext()
+    {
+        let data: Vec<u8> = b"Attack at dawn. ".repeat(16);
+        let encoded: Vec<u8> = VigenereEncoder::encode(&data, Some(&key_from_bytes(b"abc"))).expect("Encoding succeeds");
+        assert_eq!(encoded[32..].iter().zip(&data).filter(|(encoded_byte, byte)| encoded_byte == byte).count(), 0);
+        assert_eq!(VigenereEncoder::decode(&encoded, Some(&key_from_bytes(b"abc"))).expect("Decoding succeeds"), data);
+    }
+
+    #[test]
+    fn key_bytes_repeat_as_given()
+    {
+        // "Hello" shifted by "abc" (0x61, 0x62, 0x63) repeating: 0x48 + 0x61, 0x65 + 0x62, 0x6c + 0x63, 0x6c + 0x61, 0x6f + 0x62
+        let encoded: Vec<u8> = VigenereEncoder::encode(b"Hello", Some(&key_from_bytes(b"abc"))).expect("Encoding succeeds");
+        assert_eq!(encoded[32..], [0xa9, 0xc7, 0xcf, 0xcd, 0xd1]);
+
+        // Shifts wrap around: 0xff + 0x02 and 0xfe + 0x02
+        let encoded: Vec<u8> = VigenereEncoder::encode(&[0xff, 0xfe], Some(&key_from_bytes(&[0x02]))).expect("Encoding succeeds");
+        assert_eq!(encoded[32..], [0x01, 0x00]);
+    }
+}
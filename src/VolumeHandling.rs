@@ -0,0 +1,164 @@
+use std::fs;
+use std::fs::File;
+use std::io::{Read, Error, ErrorKind};
+use std::path::Path;
+
+use crate::EnvHandling::{read_file, write_file_atomically};
+
+// Every volume starts with: magic, volume number (u16, from 1), volume count (u16), archive id, CRC32 of the volume data
+const VOLUME_MAGIC: &[u8; 8] = b"RSZVOL01";
+const VOLUME_HEADER_LEN: usize = 8 + 2 + 2 + 8 + 4;
+// Volume names carry a three-digit number
+const MAX_VOLUMES: usize = 999;
+
+struct VolumeHeader
+{
+    number: usize,
+    count: usize,
+    archive_id: [u8; 8],
+    crc32: u32,
+}
+
+fn archive_id(content: &[u8]) -> [u8; 8]
+{
+    // Ties the volumes of one archive together and checks the joined content as a whole
+    return blake3::hash(content).as_bytes()[..8].try_into().expect("BLAKE3 digest is longer than 8 bytes");
+}
+
+fn volume_path(base: &str, number: usize) -> String
+{
+    return format!("{}.{:03}", base, number);
+}
+
+pub fn volume_base(filename: &str) -> &str
+{
+    // Any volume of an archive may be given instead of the archive name: file.rsz.002 stands for file.rsz
+    match filename.rsplit_once('.')
+    {
+        Some((base, number)) if number.len() == 3 && number.bytes().all(|digit| digit.is_ascii_digit()) && base.ends_with(".rsz") => { return base; }
+        _ => { return filename; }
+    }
+}
+
+fn parse_volume_header(header: &[u8]) -> Option<VolumeHeader>
+{
+    if header.len() < VOLUME_HEADER_LEN || !header.starts_with(VOLUME_MAGIC) { return None; }
+    return Some(VolumeHeader
+    {
+        number: u16::from_be_bytes([header[8], header[9]]) as usize,
+        count: u16::from_be_bytes([header[10], header[11]]) as usize,
+        archive_id: header[12..20].try_into().expect("Archive id takes 8 bytes"),
+        crc32: u32::from_be_bytes(header[20..24].try_into().expect("CRC32 takes 4 bytes")),
+    });
+}
+
+pub fn write_volumes(base: &str, content: &[u8], volume_size: usize, overwrite: bool) -> std::io::Result<Vec<String>>
+{
+    let data_size: usize = volume_size.checked_sub(VOLUME_HEADER_LEN).filter(|&data_size| data_size > 0)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("Volumes must be larger than their {} byte header", VOLUME_HEADER_LEN)))?;
+    let count: usize = content.len().div_ceil(data_size).max(1);
+    if count > MAX_VOLUMES
+    {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("File would need {} volumes, but at most {} are supported; use a larger volume size", count, MAX_VOLUMES)));
+    }
+
+    // All of them are checked first, so a refusal never leaves part of a split file behind
+    if let Some(existing) = (1..=count).map(|number| volume_path(base, number)).find(|path| !overwrite && Path::new(path).exists())
+    {
+        return Err(Error::new(ErrorKind::AlreadyExists, format!("{} already exists; use --force to overwrite it", existing)));
+    }
+
+    let id: [u8; 8] = archive_id(content);
+    let mut paths: Vec<String> = Vec::with_capacity(count);
+    for number in 1..=count
+    {
+        let data: &[u8] = content.chunks(data_size).nth(number - 1).unwrap_or_default();
+        let mut volume: Vec<u8> = Vec::with_capacity(VOLUME_HEADER_LEN + data.len());
+        volume.extend_from_slice(VOLUME_MAGIC);
+        volume.extend_from_slice(&(number as u16).to_be_bytes());
+        volume.extend_from_slice(&(count as u16).to_be_bytes());
+        volume.extend_from_slice(&id);
+        volume.extend_from_slice(&crc32fast::hash(data).to_be_bytes());
+        volume.extend_from_slice(data);
+
+        let path: String = volume_path(base, number);
+        write_file_atomically(&path, &volume, overwrite)?;
+        paths.push(path);
+    }
+    return Ok(paths);
+}
+
+fn find_volume_count(base: &str) -> std::io::Result<usize>
+{
+    // The first readable volume tells how many there are, even when the first ones are missing
+    for number in 1..=MAX_VOLUMES
+    {
+        let mut header: Vec<u8> = Vec::with_capacity(VOLUME_HEADER_LEN);
+        match File::open(volume_path(base, number))
+        {
+            Ok(file) => { file.take(VOLUME_HEADER_LEN as u64).read_to_end(&mut header)?; }
+            Err(e) if e.kind() == ErrorKind::NotFound => { continue; }
+            Err(e) => { return Err(e); }
+        }
+        if let Some(header) = parse_volume_header(&header) { return Ok(header.count); }
+    }
+    return Err(Error::new(ErrorKind::NotFound, format!("Neither {} nor any of its volumes ({}) were found", base, volume_path(base, 1))));
+}
+
+fn read_volumes(base: &str) -> std::io::Result<Vec<u8>>
+{
+    let count: usize = find_volume_count(base)?;
+
+    // Every volume is checked before giving up, so all missing or corrupt ones are reported at once
+    let mut content: Vec<u8> = Vec::new();
+    let mut problems: Vec<String> = Vec::new();
+    let mut expected_id: Option<[u8; 8]> = None;
+    for number in 1..=count
+    {
+        let path: String = volume_path(base, number);
+        let volume: Vec<u8> = match fs::read(&path)
+        {
+            Ok(volume) => volume,
+            Err(e) if e.kind() == ErrorKind::NotFound => { problems.push(format!("volume {} of {} ({}) is missing", number, count, path)); continue; }
+            Err(e) => { problems.push(format!("volume {} of {} ({}) could not be read: {}", number, count, path, e)); continue; }
+        };
+
+        match parse_volume_header(&volume)
+        {
+            Some(header) if header.number != number || header.count != count || expected_id.is_some_and(|id| id != header.archive_id) =>
+            {
+                problems.push(format!("volume {} of {} ({}) belongs to another archive or has been renamed", number, count, path));
+            }
+            Some(header) if crc32fast::hash(&volume[VOLUME_HEADER_LEN..]) != header.crc32 =>
+            {
+                problems.push(format!("volume {} of {} ({}) is corrupt", number, count, path));
+            }
+            Some(header) =>
+            {
+                expected_id = Some(header.archive_id);
+                content.extend_from_slice(&volume[VOLUME_HEADER_LEN..]);
+            }
+            None => { problems.push(format!("volume {} of {} ({}) has no volume header and is corrupt", number, count, path)); }
+        }
+    }
+
+    if !problems.is_empty()
+    {
+        return Err(Error::new(ErrorKind::InvalidData, format!("Split archive cannot be joined: {}", problems.join("; "))));
+    }
+    if expected_id.is_some_and(|id| id != archive_id(&content))
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Joined volumes do not match their archive id: volumes from different archives were mixed"));
+    }
+    return Ok(content);
+}
+
+pub fn read_encoded_file(filename: &str) -> std::io::Result<(Vec<u8>, usize)>
+{
+    // A plain .rsz file takes precedence; otherwise its numbered volumes are joined
+    let base: &str = volume_base(filename);
+    if base == filename && Path::new(base).exists() { return read_file(base); }
+    let content: Vec<u8> = read_volumes(base)?;
+    let len: usize = content.len();
+    return Ok((content, len));
+}
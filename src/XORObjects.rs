@@ -0,0 +1,74 @@
+use crate::Codec::CodecFunctions;
+
+use crate::HashHandling::{verify_hash, hash_key};
+use crate::KeyHandling::given_key_bytes;
+use crate::RZ_KEY_TYPE;
+
+fn apply_key_stream(data: &[u8], key: &RZ_KEY_TYPE) -> Vec<u8>
+{
+    // XOR is its own inverse, so the same pass encodes and decodes; the key repeats as it was given
+    let key_bytes: &[u8] = given_key_bytes(key);
+    let mut result: Vec<u8> = Vec::with_capacity(data.len());
+    for (i, &byte) in data.iter().enumerate() { result.push(byte ^ key_bytes[i % key_bytes.len()]); }
+    return result;
+}
+
+impl CodecFunctions for XOREncoder
+{
+    fn encode(data: &[u8], key: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
+    {
+        let mut result: Vec<u8> = Vec::<u8>::with_capacity(data.len() + 32);
+
+        let (key_hashed, key_ref): ([u8; 32], &RZ_KEY_TYPE) = hash_key(key);
+
+        result.extend_from_slice(&key_hashed);
+        result.extend_from_slice(&apply_key_stream(data, key_ref));
+        return Ok(result);
+    }
+
+    fn decode(encoded_data: &[u8], possible_key: Option<&RZ_KEY_TYPE>) -> std::io::Result<Vec<u8>>
+    { 
+        if encoded_data.len() < 32 { return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Encoded data too short")); }
+
+        let possible_key_ref: &RZ_KEY_TYPE = possible_key.expect("Error: No key");
+        let hashed_key: &[u8] = &encoded_data[..32];
+        
+        if !verify_hash(possible_key_ref, hashed_key) { return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "Invalid key")); }
+
+        return Ok(apply_key_stream(&encoded_data[32..], possible_key_ref));
+    }
+}
+
+pub struct XOREncoder
+{
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::KeyHandling::key_from_bytes;
+
+    #[test]
+    fn short_key_leaves_no_plaintext()
+    {
+        let data: Vec<u8> = b"Attack at dawn. ".repeat(16);
+        let encoded: Vec<u8> = XOREncoder::encode(&data, Some(&key_from_bytes(b"abc"))).expect("Encoding succeeds");
+        assert_eq!(encoded[32..].iter().zip(&data).filter(|(encoded_byte, byte)| encoded_byte == byte).count(), 0);
+        assert_eq!(XOREncoder::decode(&encoded, Some(&key_from_bytes(b"abc"))).expect("Decoding succeeds"), data);
+    }
+
+    #[test]
+    fn key_bytes_repeat_as_given()
+    {
+        // "Hello" XORed with "abc" (0x61, 0x62, 0x63) repeating: 0x48 ^ 0x61, 0x65 ^ 0x62, 0x6c ^ 0x63, 0x6c ^ 0x61, 0x6f ^ 0x62
+        let encoded: Vec<u8> = XOREncoder::encode(b"Hello", Some(&key_from_bytes(b"abc"))).expect("Encoding succeeds");
+        assert_eq!(encoded[32..], [0x29, 0x07, 0x0f, 0x0d, 0x0d]);
+
+        // A full 16-byte key repeats from its first byte on the 17th
+        let key: Vec<u8> = (1..=16).collect();
+        let encoded: Vec<u8> = XOREncoder::encode(&[0u8; 17], Some(&key_from_bytes(&key))).expect("Encoding succeeds");
+        assert_eq!(encoded[32..48], key[..]);
+        assert_eq!(encoded[48], 0x01);
+    }
+}
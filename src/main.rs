@@ -1,4 +1,9 @@
-use std::io::{self, Error, ErrorKind};
+#![allow(non_snake_case, non_camel_case_types, clippy::needless_return)]
+
+use std::io::{self, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use std::fs::File;
 use std::collections::HashMap;
 use std::hash::BuildHasherDefault;
 use fnv::FnvHasher;
@@ -8,37 +13,130 @@ pub mod HuffmanObjects;
 pub mod RLEObjects;
 pub mod CaesarObjects;
 pub mod AESObjects;
+pub mod VigenereObjects;
+pub mod XORObjects;
+pub mod RecipientObjects;
+pub mod KeySlotObjects;
 pub mod Codec;
 pub mod EnvHandling;
+pub mod CliHandling;
+pub mod BatchHandling;
+pub mod BenchHandling;
+pub mod HeaderHandling;
 pub mod HashHandling;
+pub mod KeyHandling;
+pub mod SignatureHandling;
+pub mod ConcealHandling;
+pub mod CryptanalysisHandling;
+pub mod ShareHandling;
+pub mod ChecksumHandling;
+pub mod MetadataHandling;
+pub mod ChunkHandling;
+pub mod RecoveryHandling;
+pub mod VolumeHandling;
 
 use crate::HuffmanObjects::HuffmanEncoder;
 use crate::RLEObjects::RLEEncoder;
 use crate::CaesarObjects::CaesarEncoder;
 use crate::AESObjects::AESEncoder;
+use crate::VigenereObjects::VigenereEncoder;
+use crate::XORObjects::XOREncoder;
+use crate::RecipientObjects::RecipientEncoder;
+use crate::KeySlotObjects::{KeySlotEncoder, KeySlotTable, Credential};
 
 use crate::Codec::CodecList;
-use crate::Codec::CodecFunctions;
+use crate::Codec::{CodecFunctions, StageParams};
 
-use crate::EnvHandling::write_decoded_file;
 use crate::EnvHandling::write_encoded_file;
 use crate::EnvHandling::read_file;
-use crate::EnvHandling::validate_encoded_file;
+use crate::EnvHandling::replace_file_atomically;
+use crate::EnvHandling::{HeaderOptions, OutputFile, OutputOptions, to_hex, STDIO_PATH};
+use crate::CliHandling::{BenchPipeline, Command, DecodePlan, EncodePlan, read_keys};
+use crate::BatchHandling::{BatchInput, run_batch};
+use crate::BenchHandling::{BenchOutcome, BenchRow, PeakMemory, print_table};
+use crate::HeaderHandling::{EncodedHeader, FixedHeader, HeaderField, parse_encoded_header, parse_fixed_header, serialize_encoded_file, FLAG_CHUNKED, FLAG_STREAMED, FORMAT_MAGIC};
+
+use crate::ConcealHandling::{is_concealed, conceal_container, reveal_container};
+use crate::KeyHandling::resolve_key;
+use crate::HashHandling::wipe_and_replace;
+use zeroize::Zeroizing;
+use crate::ShareHandling::write_shares;
+use crate::ChecksumHandling::{ChecksumAlgorithm, checksum_field, verify_checksum, stored_checksum, streamed_checksum, verify_streamed_checksum, StreamingChecksum, FIELD_CHECKSUM, FIELD_STREAMED_CHECKSUM};
+use crate::MetadataHandling::{FileMetadata, format_unix_time, FIELD_FILE_NAME, FIELD_FILE_MODE, FIELD_FILE_MTIME, FIELD_FILE_XATTR};
+use crate::ChunkHandling::{EncodedChunk, frame_chunks, decode_chunks, map_chunks, read_range, seek_table, write_frame, write_stream_end, read_stream, map_frames, stream_frames};
+use crate::RecoveryHandling::{add_recovery_record, check_recovery_record, split_recovery_record, RecoveryRecord, RECOVERY_FOOTER_LEN};
+use crate::VolumeHandling::{read_encoded_file, volume_base};
+use crate::CryptanalysisHandling::{ReferenceModel, ShiftCandidate, break_caesar};
+use crate::SignatureHandling::{check_embedded_signature, split_signature_trailer, generate_signing_keypair, sign_file, verify_file, TRAILER_LEN, TRAILER_MAGIC};
 
 pub type DetHashMap<K, V> = HashMap<K, V, BuildHasherDefault<FnvHasher>>;
 
-pub type RZ_KEY_TYPE = i128;
+pub type RZ_KEY_TYPE = HashHandling::SecretKey;
 pub const KEY_LENGTH_BYTES: usize = 16;
 pub const KEY_LENGTH_BITS: usize = KEY_LENGTH_BYTES * 8;
 
-fn encode_file(filepath: &str, codecs: &[u8], keys: &[RZ_KEY_TYPE]) -> io::Result<()> 
+fn encode_file(filepath: &str, plan: &EncodePlan, pad: bool, header_options: &HeaderOptions, output_options: &OutputOptions) -> io::Result<(usize, usize)> 
 {
+    // Returns the bytes read and written
+    let EncodePlan { codecs, keys, recipients, key_slots, .. } = plan;
+    let conceal: Option<(&RZ_KEY_TYPE, bool)> = plan.conceal.as_ref().map(|conceal_key| (conceal_key, pad));
+    // Refused before encoding anything; the input file is always kept
+    let output_path: String = output_options.path.clone().unwrap_or_else(|| format!("{}.rsz", filepath));
+    if !output_options.force && output_path != STDIO_PATH && header_options.volume_size.is_none() && Path::new(&output_path).exists()
+    {
+        let e: Error = Error::new(ErrorKind::AlreadyExists, format!("{} already exists; use --force to overwrite it", output_path));
+        eprintln!("An error occurred while saving encoded file: {}", e);
+        return Err(e);
+    }
+
     match read_file(filepath)
     {
-        Ok((mut global_buffer, original_len)) =>
+        Ok((global_buffer, original_len)) =>
         {
+            // Every intermediate stage may hold plaintext, so the buffer is wiped whenever it is replaced or dropped
+            let mut global_buffer: Zeroizing<Vec<u8>> = Zeroizing::new(global_buffer);
+            let mut fields: Vec<HeaderField> = header_options.checksum.map(|algorithm| checksum_field(algorithm, &global_buffer)).into_iter().collect();
+            if header_options.metadata
+            {
+                match FileMetadata::collect(filepath, header_options.xattrs)
+                {
+                    Ok(metadata) => { fields.extend(metadata.to_fields()); }
+                    Err(e) =>
+                    {
+                        eprintln!("An error occurred while reading file metadata: {}", e);
+                        return Err(e);
+                    }
+                }
+            }
             let mut i_current_key: usize = 0;
-            for &codec_byte in codecs
+            let mut i_current_recipients: usize = 0;
+            let mut key_slot_table: Option<KeySlotTable> = None;
+            let mut stage_params: Vec<StageParams> = Vec::with_capacity(codecs.len());
+
+            // Chunked files run the whole pipeline on every chunk here, so the stage loop below is skipped
+            let mut flags: u16 = 0;
+            let mut pipeline_codecs: &[u8] = codecs;
+            if let Some(chunk_size) = header_options.chunk_size
+            {
+                match encode_chunked(&global_buffer, codecs, keys, chunk_size)
+                {
+                    Ok(chunked_data) =>
+                    {
+                        // Every chunk records its own stage parameters in the seek table, so the header keeps empty ones
+                        wipe_and_replace(&mut global_buffer, chunked_data);
+                        stage_params = vec![StageParams::new(); codecs.len()];
+                        flags = FLAG_CHUNKED;
+                        pipeline_codecs = &[];
+                    }
+                    Err(e) =>
+                    {
+                        eprintln!("An error occurred while encoding file in chunks: {}", e);
+                        return Err(e);
+                    }
+                }
+            }
+
+            for &codec_byte in pipeline_codecs
             {
                 let current_codec: CodecList = CodecList::try_from(codec_byte)
                     .map_err(|_| Error::new(ErrorKind::InvalidData, "Invalid codec found"))?;
@@ -47,9 +145,13 @@ fn encode_file(filepath: &str, codecs: &[u8], keys: &[RZ_KEY_TYPE]) -> io::Resul
                 {
                     CodecList::Huffman => 
                     {
-                        match HuffmanEncoder::encode(&global_buffer, None)
+                        match HuffmanEncoder::encode_with_params(&global_buffer, None)
                         {
-                            Ok(huffman_encoded_data) => { global_buffer = huffman_encoded_data; }
+                            Ok((huffman_encoded_data, params)) =>
+                            {
+                                wipe_and_replace(&mut global_buffer, huffman_encoded_data);
+                                stage_params.push(params);
+                            }
                             Err(e) => 
                             {
                                 eprintln!("An error occurred while encoding file with Huffman: {}", e);
@@ -59,9 +161,13 @@ fn encode_file(filepath: &str, codecs: &[u8], keys: &[RZ_KEY_TYPE]) -> io::Resul
                     }
                     CodecList::RLE =>
                     {
-                        match RLEEncoder::encode(&global_buffer, None)
+                        match RLEEncoder::encode_with_params(&global_buffer, None)
                         {
-                            Ok(rle_encoded_data) => { global_buffer = rle_encoded_data; }
+                            Ok((rle_encoded_data, params)) =>
+                            {
+                                wipe_and_replace(&mut global_buffer, rle_encoded_data);
+                                stage_params.push(params);
+                            }
                             Err(e) => 
                             {
                                 eprintln!("An error occurred while encoding file with RLE: {}", e);
@@ -72,11 +178,12 @@ fn encode_file(filepath: &str, codecs: &[u8], keys: &[RZ_KEY_TYPE]) -> io::Resul
                     CodecList::Caesar =>
                     {
 
-                        match CaesarEncoder::encode(&global_buffer, Some(&keys[i_current_key]))
+                        match CaesarEncoder::encode_with_params(&global_buffer, Some(&keys[i_current_key]))
                         {
-                            Ok(caesar_encoded_data) => 
+                            Ok((caesar_encoded_data, params)) => 
                             { 
-                                global_buffer = caesar_encoded_data;
+                                wipe_and_replace(&mut global_buffer, caesar_encoded_data);
+                                stage_params.push(params);
                                 i_current_key += 1;
                             }
                             Err(e) =>
@@ -88,11 +195,12 @@ fn encode_file(filepath: &str, codecs: &[u8], keys: &[RZ_KEY_TYPE]) -> io::Resul
                     }
                     CodecList::AES =>
                     {
-                        match AESEncoder::encode(&global_buffer, Some(&keys[i_current_key]))
+                        match AESEncoder::encode_with_params(&global_buffer, Some(&keys[i_current_key]))
                         {
-                            Ok(aes_encoded_data) => 
+                            Ok((aes_encoded_data, params)) => 
                             { 
-                                global_buffer = aes_encoded_data;
+                                wipe_and_replace(&mut global_buffer, aes_encoded_data);
+                                stage_params.push(params);
                                 i_current_key += 1;
                             }
                             Err(e) =>
@@ -102,127 +210,73 @@ fn encode_file(filepath: &str, codecs: &[u8], keys: &[RZ_KEY_TYPE]) -> io::Resul
                             }
                         }
                     }
-                    _ => { return Err(Error::new(ErrorKind::InvalidData, "Invalid codec found")); }
-                }
-            }
-
-            match write_encoded_file(filepath, &global_buffer, &codecs)
-            {
-                Ok(_) => { return Ok(()); }
-                Err(e) => 
-                {
-                    eprintln!("An error occurred while saving encoded file: {}", e);
-                    return Err(e);
-                }
-            }
-        }
-
-        Err(e) => 
-        {
-            eprintln!("An error occurred while decoding file: {}", e);
-            return Err(e);
-        }
-    }
-}
-
-fn decode_file(filepath: &str, keys: &[RZ_KEY_TYPE]) -> std::io::Result<()>
-{
-    match read_file(filepath) 
-    {
-        Ok((mut global_buffer, original_len)) => 
-        {
-            match validate_encoded_file(global_buffer[0])
-            {
-                Ok(()) => {}
-                Err(e) => 
-                {
-                    eprintln!("An error occurred while validating file: {}", e);
-                    return Err(e);
-                }
-            }
-            
-            let mut current_byte: usize = 1;
-
-            let codecs_len: usize = global_buffer[current_byte] as usize;
-            current_byte += 1;
-
-            let mut codecs: Vec<u8> = global_buffer[current_byte..current_byte+codecs_len].to_vec();
-            codecs.reverse();
-
-            current_byte += codecs_len;
-
-            let mut i_current_key: usize = 0;
-            for codec_byte in codecs
-            {
-                let subbuffer = &global_buffer[current_byte..];
-
-                let current_codec: CodecList = CodecList::try_from(codec_byte)
-                    .map_err(|_| Error::new(ErrorKind::InvalidData, "Invalid codec found"))?;
-
-                match current_codec 
-                {
-                    CodecList::Huffman => 
-                    { 
-                        match HuffmanEncoder::decode(&subbuffer.to_vec(), None)
+                    CodecList::Vigenere =>
+                    {
+                        match VigenereEncoder::encode_with_params(&global_buffer, Some(&keys[i_current_key]))
                         {
-                            Ok(huffman_decoded_data) => 
+                            Ok((vigenere_encoded_data, params)) => 
                             { 
-                                global_buffer = huffman_decoded_data;
-                                current_byte = 0;
+                                wipe_and_replace(&mut global_buffer, vigenere_encoded_data);
+                                stage_params.push(params);
+                                i_current_key += 1;
                             }
-                            Err(e) => 
+                            Err(e) =>
                             {
-                                eprintln!("An error occurred while decoding file with Huffman: {}", e);
+                                eprintln!("An error occurred while encoding file with Vigenere: {}", e);
                                 return Err(e);
                             }
                         }
                     }
-                    CodecList::RLE =>
+                    CodecList::XOR =>
                     {
-                        match RLEEncoder::decode(&subbuffer.to_vec(), None)
+                        match XOREncoder::encode_with_params(&global_buffer, Some(&keys[i_current_key]))
                         {
-                            Ok(rle_decoded_data) =>
-                            {
-                                global_buffer = rle_decoded_data;
-                                current_byte = 0;
+                            Ok((xor_encoded_data, params)) => 
+                            { 
+                                wipe_and_replace(&mut global_buffer, xor_encoded_data);
+                                stage_params.push(params);
+                                i_current_key += 1;
                             }
-                            Err(e) => 
+                            Err(e) =>
                             {
-                                eprintln!("An error occurred while decoding file with RLE: {}", e);
+                                eprintln!("An error occurred while encoding file with XOR: {}", e);
                                 return Err(e);
                             }
                         }
                     }
-                    CodecList::Caesar =>
+                    CodecList::X25519 =>
                     {
-                        match CaesarEncoder::decode(&subbuffer.to_vec(), Some(&keys[i_current_key]))
+                        let recipients_keys = recipients[i_current_recipients].iter()
+                            .map(|recipient_file| RecipientEncoder::load_recipient(recipient_file))
+                            .collect::<io::Result<Vec<_>>>()?;
+                        match RecipientEncoder::encode(&global_buffer, &recipients_keys)
                         {
-                            Ok(caesar_decoded_data) =>
+                            Ok(x25519_encoded_data) =>
                             {
-                                global_buffer = caesar_decoded_data;
-                                current_byte = 0;
-                                i_current_key += 1;
+                                wipe_and_replace(&mut global_buffer, x25519_encoded_data);
+                                stage_params.push(StageParams::new());
+                                i_current_recipients += 1;
                             }
-                            Err(e) => 
+                            Err(e) =>
                             {
-                                eprintln!("An error occurred while decoding file with Caesar: {}", e);
+                                eprintln!("An error occurred while encoding file for recipients: {}", e);
                                 return Err(e);
                             }
                         }
                     }
-                    CodecList::AES =>
+                    CodecList::KeySlots =>
                     {
-                        match AESEncoder::decode(&subbuffer.to_vec(), Some(&keys[i_current_key]))
+                        match KeySlotEncoder::encode(&global_buffer, key_slots)
                         {
-                            Ok(aes_decoded_data) =>
+                            Ok((key_slots_encoded_data, table)) =>
                             {
-                                global_buffer = aes_decoded_data;
-                                current_byte = 0;
-                                i_current_key += 1;
+                                wipe_and_replace(&mut global_buffer, key_slots_encoded_data);
+                                stage_params.push(StageParams::new());
+                                key_slot_table = Some(table);
                             }
-                            Err(e) => 
+                            Err(e) =>
                             {
-                                eprintln!("An error occurred while decoding file with AES: {}", e);
+                                eprintln!("An error occurred while encoding file with key slots: {}", e);
                                 return Err(e);
                             }
                         }
@@ -230,17 +284,27 @@ fn decode_file(filepath: &str, keys: &[RZ_KEY_TYPE]) -> std::io::Result<()>
                     _ => { return Err(Error::new(ErrorKind::InvalidData, "Invalid codec found")); }
                 }
             }
-            
-            match write_decoded_file(filepath, &global_buffer)
+
+            let mut container: Vec<u8> = serialize_encoded_file(&global_buffer, flags, codecs, &stage_params, key_slot_table.as_ref(), &fields);
+            if let Some((conceal_key, pad)) = conceal
             {
-                Ok(()) => 
+                match conceal_container(&container, conceal_key, pad)
                 {
-                    println!("File decoded and saved.");
-                    return Ok(());
+                    Ok(concealed_container) => { container = concealed_container; }
+                    Err(e) =>
+                    {
+                        eprintln!("An error occurred while concealing file header: {}", e);
+                        return Err(e);
+                    }
                 }
+            }
+
+            match write_encoded_file(&output_path, &container, header_options, output_options.force)
+            {
+                Ok(written_len) => { return Ok((original_len, written_len)); }
                 Err(e) => 
                 {
-                    eprintln!("Decoded content could not be saved due to an error: {}", e);
+                    eprintln!("An error occurred while saving encoded file: {}", e);
                     return Err(e);
                 }
             }
@@ -254,29 +318,1273 @@ fn decode_file(filepath: &str, keys: &[RZ_KEY_TYPE]) -> std::io::Result<()>
     }
 }
 
-fn main() -> io::Result<()>
+fn decode_container(global_buffer: Vec<u8>, plan: &DecodePlan) -> io::Result<(Zeroizing<Vec<u8>>, Vec<HeaderField>)>
 {
-    match EnvHandling::check_entry()
+    let DecodePlan { keys, identities, shares, signer } = plan;
+    // Runs every stage back, returning the content with the header fields that describe it; the checksum is left to the caller
+    let mut global_buffer: Zeroizing<Vec<u8>> = Zeroizing::new(global_buffer);
+
+    // Damage is reported before checking the signature, which could only tell that the file does not match
+    let container_len: usize = match check_recovery_record(split_signature_trailer(&global_buffer).0)
+    {
+        Ok((container, _)) => container.len(),
+        Err(e) =>
+        {
+            eprintln!("An error occurred while checking recovery record: {}", e);
+            return Err(e);
+        }
+    };
+    match check_embedded_signature(&global_buffer, signer.as_ref())
+    {
+        Ok(_) => { global_buffer.truncate(container_len); }
+        Err(e) =>
+        {
+            eprintln!("An error occurred while verifying file signature: {}", e);
+            return Err(e);
+        }
+    }
+
+    // A concealed header is opened with the first key, as it is the outermost layer
+    let mut keys: &[RZ_KEY_TYPE] = keys;
+    if is_concealed(&global_buffer)
+    {
+        let conceal_key: &RZ_KEY_TYPE = keys.first()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "File header is concealed and no key was given"))?;
+        match reveal_container(&global_buffer, conceal_key)
+        {
+            Ok((container, _)) => { wipe_and_replace(&mut global_buffer, container); }
+            Err(e) =>
+            {
+                eprintln!("An error occurred while revealing file header: {}", e);
+                return Err(e);
+            }
+        }
+        keys = &keys[1..];
+    }
+
+    let header: EncodedHeader = match parse_encoded_header(&global_buffer)
+    {
+        Ok(header) => header,
+        Err(e) => 
+        {
+            eprintln!("An error occurred while validating file: {}", e);
+            return Err(e);
+        }
+    };
+
+    // Every encrypted stage takes one key; a key slot stage may take one more, as its password
+    let keys_needed: usize = header.keys_needed();
+    if keys.len() < keys_needed
+    {
+        let e: Error = Error::new(ErrorKind::InvalidInput, format!("File has {} encrypted stage(s), but only {} key(s) were given", keys_needed, keys.len()));
+        eprintln!("An error occurred while validating file: {}", e);
+        return Err(e);
+    }
+    let EncodedHeader { flags, mut codecs, mut stage_params, key_slots, mut fields, mut payload_offset } = header;
+
+    // Chunked payloads are decoded chunk by chunk here, so no stage is left for the loop below
+    if flags & FLAG_CHUNKED != 0
+    {
+        let decoded_data: io::Result<Vec<u8>> = chunk_decoder(&codecs, keys)
+            .and_then(|decode_chunk| decode_chunks(&global_buffer[payload_offset..], decode_chunk));
+        match decoded_data
+        {
+            Ok(decoded_data) =>
+            {
+                wipe_and_replace(&mut global_buffer, decoded_data);
+                codecs.clear();
+                stage_params.clear();
+                payload_offset = 0;
+            }
+            Err(e) =>
+            {
+                eprintln!("An error occurred while decoding chunked file: {}", e);
+                return Err(e);
+            }
+        }
+    }
+    // Streamed payloads are read frame by frame; the checksum record at their end is verified like a header checksum
+    else if flags & FLAG_STREAMED != 0
+    {
+        let mut decoded_data: Zeroizing<Vec<u8>> = Zeroizing::new(Vec::new());
+        let mut payload: &[u8] = &global_buffer[payload_offset..];
+        let checksum_record: io::Result<Vec<u8>> = chunk_decoder(&codecs, keys)
+            .and_then(|decode_chunk| read_stream(&mut payload, decode_chunk, |chunk| { decoded_data.extend_from_slice(chunk); return Ok(()); }))
+            .and_then(|checksum_record| check_stream_end(&fields, checksum_record, payload.len()));
+        match checksum_record
+        {
+            Ok(checksum_record) =>
+            {
+                if !checksum_record.is_empty() { fields.push(HeaderField { kind: FIELD_CHECKSUM, value: checksum_record }); }
+                wipe_and_replace(&mut global_buffer, std::mem::take(&mut *decoded_data));
+                codecs.clear();
+                stage_params.clear();
+                payload_offset = 0;
+            }
+            Err(e) =>
+            {
+                eprintln!("An error occurred while decoding streamed file: {}", e);
+                return Err(e);
+            }
+        }
+    }
+    codecs.reverse();
+    stage_params.reverse();
+
+    let mut current_byte: usize = payload_offset;
+
+    let mut i_current_key: usize = 0;
+    for (codec_byte, params) in codecs.into_iter().zip(stage_params)
     {
-        Some((mode, filepath, codecs, keys)) => 
-        {   
-            if let Some(keys_vec) = keys.as_ref()
+        let subbuffer = &global_buffer[current_byte..];
+
+        let current_codec: CodecList = CodecList::try_from(codec_byte)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "Invalid codec found"))?;
+
+        match current_codec 
+        {
+            CodecList::Huffman => 
+            { 
+                match HuffmanEncoder::decode_with_params(subbuffer, None, &params)
+                {
+                    Ok(huffman_decoded_data) => 
+                    { 
+                        wipe_and_replace(&mut global_buffer, huffman_decoded_data);
+                        current_byte = 0;
+                    }
+                    Err(e) => 
+                    {
+                        eprintln!("An error occurred while decoding file with Huffman: {}", e);
+                        return Err(e);
+                    }
+                }
+            }
+            CodecList::RLE =>
+            {
+                match RLEEncoder::decode_with_params(subbuffer, None, &params)
+                {
+                    Ok(rle_decoded_data) =>
+                    {
+                        wipe_and_replace(&mut global_buffer, rle_decoded_data);
+                        current_byte = 0;
+                    }
+                    Err(e) => 
+                    {
+                        eprintln!("An error occurred while decoding file with RLE: {}", e);
+                        return Err(e);
+                    }
+                }
+            }
+            CodecList::Caesar =>
+            {
+                match CaesarEncoder::decode_with_params(subbuffer, Some(&keys[i_current_key]), &params)
+                {
+                    Ok(caesar_decoded_data) =>
+                    {
+                        wipe_and_replace(&mut global_buffer, caesar_decoded_data);
+                        current_byte = 0;
+                        i_current_key += 1;
+                    }
+                    Err(e) => 
+                    {
+                        eprintln!("An error occurred while decoding file with Caesar: {}", e);
+                        return Err(e);
+                    }
+                }
+            }
+            CodecList::AES =>
+            {
+                match AESEncoder::decode_with_params(subbuffer, Some(&keys[i_current_key]), &params)
+                {
+                    Ok(aes_decoded_data) =>
+                    {
+                        wipe_and_replace(&mut global_buffer, aes_decoded_data);
+                        current_byte = 0;
+                        i_current_key += 1;
+                    }
+                    Err(e) => 
+                    {
+                        eprintln!("An error occurred while decoding file with AES: {}", e);
+                        return Err(e);
+                    }
+                }
+            }
+            CodecList::Vigenere =>
+            {
+                match VigenereEncoder::decode_with_params(subbuffer, Some(&keys[i_current_key]), &params)
+                {
+                    Ok(vigenere_decoded_data) =>
+                    {
+                        wipe_and_replace(&mut global_buffer, vigenere_decoded_data);
+                        current_byte = 0;
+                        i_current_key += 1;
+                    }
+                    Err(e) => 
+                    {
+                        eprintln!("An error occurred while decoding file with Vigenere: {}", e);
+                        return Err(e);
+                    }
+                }
+            }
+            CodecList::XOR =>
+            {
+                match XOREncoder::decode_with_params(subbuffer, Some(&keys[i_current_key]), &params)
+                {
+                    Ok(xor_decoded_data) =>
+                    {
+                        wipe_and_replace(&mut global_buffer, xor_decoded_data);
+                        current_byte = 0;
+                        i_current_key += 1;
+                    }
+                    Err(e) => 
+                    {
+                        eprintln!("An error occurred while decoding file with XOR: {}", e);
+                        return Err(e);
+                    }
+                }
+            }
+            CodecList::X25519 =>
+            {
+                let identities_keys = identities.iter()
+                    .map(|identity_file| RecipientEncoder::load_identity(identity_file))
+                    .collect::<io::Result<Vec<_>>>()?;
+                match RecipientEncoder::decode(subbuffer, &identities_keys)
+                {
+                    Ok(x25519_decoded_data) =>
+                    {
+                        wipe_and_replace(&mut global_buffer, x25519_decoded_data);
+                        current_byte = 0;
+                    }
+                    Err(e) => 
+                    {
+                        eprintln!("An error occurred while decoding file for recipient: {}", e);
+                        return Err(e);
+                    }
+                }
+            }
+            CodecList::KeySlots =>
             {
-                if mode == "-e" 
+                let table: &KeySlotTable = key_slots.as_ref()
+                    .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Key slot table is missing"))?;
+
+                // Identities are tried first, then shares; otherwise the next key is taken as a slot password
+                let mut credentials: Vec<Credential> = identities.iter()
+                    .map(|identity_file| RecipientEncoder::load_identity(identity_file).map(Credential::Identity))
+                    .collect::<io::Result<Vec<_>>>()?;
+                let data_key: RZ_KEY_TYPE = match table.unlock(&credentials)
+                {
+                    Ok(data_key) => data_key,
+                    Err(_) if !shares.is_empty() =>
+                    {
+                        table.unlock_with_shares(shares).inspect_err(|e| eprintln!("An error occurred while decoding file with key shares: {}", e))?
+                    }
+                    Err(e) if keys.len() <= keys_needed => 
+                    {
+                        eprintln!("An error occurred while decoding file with key slots: {}", e);
+                        return Err(e);
+                    }
+                    Err(_) =>
+                    {
+                        credentials = vec![Credential::Password(keys[i_current_key].clone())];
+                        i_current_key += 1;
+                        table.unlock(&credentials).inspect_err(|e| eprintln!("An error occurred while decoding file with key slots: {}", e))?
+                    }
+                };
+
+                match KeySlotEncoder::decode(subbuffer, &data_key)
                 {
-                    if let Some(codecs_vec) = codecs.as_ref() { encode_file(&filepath, codecs_vec, keys_vec); }
-                    else 
+                    Ok(key_slots_decoded_data) =>
+                    {
+                        wipe_and_replace(&mut global_buffer, key_slots_decoded_data);
+                        current_byte = 0;
+                    }
+                    Err(e) => 
                     {
-                        eprintln!("No codecs specified for encoding");
-                        std::process::exit(1);
+                        eprintln!("An error occurred while decoding file with key slots: {}", e);
+                        return Err(e);
                     }
                 }
-                else if mode == "-d" { decode_file(&filepath, keys_vec); }
             }
-            else { std::process::exit(1); }
+            _ => { return Err(Error::new(ErrorKind::InvalidData, "Invalid codec found")); }
+        }
+    }
+    return Ok((global_buffer, fields));
+}
+
+fn report(output: &OutputFile, message: &str)
+{
+    // Standard output only carries content when it is the output
+    if output.is_stdout() { eprintln!("{}", message); } else { println!("{}", message); }
+}
+
+fn save_decoded(content: &[u8], fields: &[HeaderField], output_path: &str, output_options: &OutputOptions, ignore_checksum: bool, metadata: &FileMetadata) -> io::Result<usize>
+{
+    let mut output: OutputFile = OutputFile::create(output_path, output_options.force)?;
+
+    // Mismatching output is only written when asked for
+    match verify_checksum(fields, content)
+    {
+        Ok(Some(algorithm)) => { report(&output, &format!("Content verified ({} checksum and length).", algorithm.name())); }
+        Ok(None) => { report(&output, "File stores no checksum; content could not be verified."); }
+        Err(e) if ignore_checksum => { eprintln!("Warning: {}. Saving anyway because of --ignore-checksum.", e); }
+        Err(e) => { return Err(Error::new(e.kind(), format!("decoded content is corrupted and has not been saved: {}", e))); }
+    }
+
+    // Metadata is restored on the temporary file, so the result appears complete or not at all
+    output.write_all(content)?;
+    if output.is_stdout() { output.commit()?; return Ok(content.len()); }
+    output.commit_with(|temp_path| metadata.apply(temp_path))?;
+    println!("File decoded and saved to {}.", output_path);
+    return Ok(content.len());
+}
+
+fn decode_file(filepath: &str, plan: &DecodePlan, output_options: &OutputOptions, ignore_checksum: bool, restore_metadata: bool) -> std::io::Result<(usize, usize)>
+{
+    // Returns the bytes read and written
+    // Any volume of a split file stands for the whole file
+    let filepath: &str = volume_base(filepath);
+    match read_encoded_file(filepath) 
+    {
+        Ok((global_buffer, encoded_len)) => 
+        {
+            let (global_buffer, fields) = decode_container(global_buffer, plan)?;
+
+            // Ignored metadata still leaves the name to be derived from the .rsz one; the encoded file is always kept
+            let metadata: FileMetadata = if restore_metadata { FileMetadata::from_fields(&fields) } else { FileMetadata::default() };
+            let output_path: String = output_options.path.clone().unwrap_or_else(|| metadata.decoded_path(filepath));
+            return save_decoded(&global_buffer, &fields, &output_path, output_options, ignore_checksum, &metadata)
+                .map(|decoded_len| (encoded_len, decoded_len))
+                .inspect_err(|e| eprintln!("Decoded content could not be saved due to an error: {}", e));
+        }
+
+        Err(e) => 
+        {
+            eprintln!("An error occurred while decoding file: {}", e);
+            return Err(e);
+        }
+    }
+}
 
-            return Ok(());
+fn test_file(filepath: &str, plan: &DecodePlan) -> io::Result<(usize, usize)>
+{
+    // The whole pipeline runs in memory and nothing is written, so archives can be checked where they are stored
+    let result: io::Result<(usize, usize, Option<ChecksumAlgorithm>)> = read_encoded_file(volume_base(filepath)).and_then(|(global_buffer, encoded_len)|
+    {
+        let (content, fields) = decode_container(global_buffer, plan)?;
+        let algorithm: Option<ChecksumAlgorithm> = verify_checksum(&fields, &content)?;
+        return Ok((encoded_len, content.len(), algorithm));
+    });
+    match result
+    {
+        Ok((encoded_len, decoded_len, Some(algorithm))) =>
+        {
+            println!("{}: OK ({} checksum and length)", filepath, algorithm.name());
+            return Ok((encoded_len, decoded_len));
+        }
+        Ok((encoded_len, decoded_len, None)) =>
+        {
+            println!("{}: OK (decoded; no checksum stored to verify)", filepath);
+            return Ok((encoded_len, decoded_len));
+        }
+        Err(e) =>
+        {
+            println!("{}: FAIL ({})", filepath, e);
+            return Err(e);
         }
-        None => { std::process::exit(1); }
     }
-}
\ No newline at end of file
+}
+
+fn check_stream_end(fields: &[HeaderField], checksum_record: Vec<u8>, trailing_len: usize) -> io::Result<Vec<u8>>
+{
+    if streamed_checksum(fields)?.is_some() && checksum_record.is_empty()
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Stream ends without the checksum its header announces"));
+    }
+    if trailing_len > 0 { return Err(Error::new(ErrorKind::InvalidData, format!("{} bytes follow the end of the stream", trailing_len))); }
+    return Ok(checksum_record);
+}
+
+fn decode_stream(plan: &DecodePlan, output_options: &OutputOptions, ignore_checksum: bool) -> io::Result<()>
+{
+    // Streamed containers are decoded while they are read; any other container is read whole first
+    // Output goes to standard output unless a path is given, and the original name and metadata are not restored
+    let output_path: &str = output_options.path.as_deref().unwrap_or(STDIO_PATH);
+    let mut input = io::stdin().lock();
+    let mut header: Vec<u8> = Vec::with_capacity(16);
+    (&mut input).take(16).read_to_end(&mut header)?;
+    let streamed: bool = header.len() == 16 && header.starts_with(FORMAT_MAGIC) && u16::from_be_bytes([header[10], header[11]]) & FLAG_STREAMED != 0;
+    if !streamed
+    {
+        input.read_to_end(&mut header)?;
+        let (content, fields) = decode_container(header, plan)?;
+        return save_decoded(&content, &fields, output_path, output_options, ignore_checksum, &FileMetadata::default()).map(|_| ());
+    }
+
+    let header_len: u64 = u32::from_be_bytes(header[12..16].try_into().expect("Header length takes 4 bytes")) as u64;
+    (&mut input).take(header_len.saturating_sub(16)).read_to_end(&mut header)?;
+    let header: EncodedHeader = parse_encoded_header(&header)?;
+    if plan.signer.is_some()
+    {
+        return Err(Error::new(ErrorKind::InvalidInput, "Streamed input carries no signature to check; sign and verify the file instead"));
+    }
+    let keys: &[RZ_KEY_TYPE] = &plan.keys;
+    if keys.len() < header.keys_needed()
+    {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("File has {} encrypted stage(s), but only {} key(s) were given", header.keys_needed(), keys.len())));
+    }
+
+    // Every chunk is checked against its length and CRC32 before it is written; the whole content can only be checked at the end,
+    // which still keeps a file output from taking its name
+    let mut output: OutputFile = OutputFile::create(output_path, output_options.force)?;
+    let mut checksum: Option<StreamingChecksum> = streamed_checksum(&header.fields)?.map(StreamingChecksum::new);
+    let decode_chunk = chunk_decoder(&header.codecs, keys)?;
+    let checksum_record: Vec<u8> = read_stream(&mut input, decode_chunk, |chunk|
+    {
+        if let Some(checksum) = &mut checksum { checksum.update(chunk); }
+        return output.write_all(chunk);
+    })?;
+
+    let mut trailing: Vec<u8> = Vec::new();
+    input.read_to_end(&mut trailing)?;
+    let checksum_record: Vec<u8> = check_stream_end(&header.fields, checksum_record, 0)?;
+    match checksum
+    {
+        Some(checksum) => match verify_streamed_checksum(&checksum_record, checksum)
+        {
+            Ok(algorithm) => { report(&output, &format!("Content verified ({} checksum and length).", algorithm.name())); }
+            Err(e) if ignore_checksum => { eprintln!("Warning: {}. Saving anyway because of --ignore-checksum.", e); }
+            Err(e) if output.is_stdout() => { return Err(Error::new(e.kind(), format!("content written to standard output is corrupted: {}", e))); }
+            Err(e) => { return Err(Error::new(e.kind(), format!("decoded content is corrupted and has not been saved: {}", e))); }
+        },
+        None => { report(&output, "Stream stores no checksum; every chunk was checked against its length and CRC32."); }
+    }
+    if !trailing.is_empty() { eprintln!("Warning: {} bytes after the end of the stream were not checked; signatures and recovery records are only read from files.", trailing.len()); }
+    if output.is_stdout() { return output.commit(); }
+    output.commit()?;
+    println!("File decoded and saved to {}.", output_path);
+    return Ok(());
+}
+
+fn reveal_for_edit(buffer: &[u8], conceal_arg: Option<&str>) -> io::Result<Option<(Vec<u8>, RZ_KEY_TYPE, bool)>>
+{
+    // Returns the revealed container of a concealed file, with the key and padding to seal it again
+    if !is_concealed(buffer)
+    {
+        if conceal_arg.is_some() { return Err(Error::new(ErrorKind::InvalidInput, "File header is not concealed, so no concealing key is needed")); }
+        return Ok(None);
+    }
+    let conceal_arg: &str = conceal_arg
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "File header is concealed; give its key with --conceal"))?;
+    let conceal_key: RZ_KEY_TYPE = resolve_key(conceal_arg, "Concealing key", false)?;
+    let (container, padded) = reveal_container(buffer, &conceal_key)?;
+    return Ok(Some((container, conceal_key, padded)));
+}
+
+fn modify_key_slots(filepath: &str, credential_spec: &str, conceal_arg: Option<&str>, modification: impl FnOnce(&mut KeySlotTable, &RZ_KEY_TYPE) -> io::Result<()>) -> io::Result<()>
+{
+    let (full_buffer, _) = read_file(filepath)?;
+    let (buffer, signature) = split_signature_trailer(&full_buffer);
+    let (buffer, recovery) = check_recovery_record(buffer)?;
+    let revealed: Option<(Vec<u8>, RZ_KEY_TYPE, bool)> = reveal_for_edit(buffer, conceal_arg)?;
+    let buffer: &[u8] = revealed.as_ref().map_or(buffer, |(container, _, _)| container);
+    let EncodedHeader { flags, codecs, stage_params, key_slots, fields, payload_offset } = parse_encoded_header(buffer)?;
+    let mut table: KeySlotTable = key_slots
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "File has no key slots"))?;
+
+    let data_key: RZ_KEY_TYPE = table.unlock(&[Credential::from_spec(credential_spec)?])?;
+    modification(&mut table, &data_key)?;
+
+    // Payload is left untouched: only the header with the slot table is rewritten
+    let mut content: Vec<u8> = serialize_encoded_file(&buffer[payload_offset..], flags, &codecs, &stage_params, Some(&table), &fields);
+    if let Some((_, conceal_key, padded)) = &revealed { content = conceal_container(&content, conceal_key, *padded)?; }
+    if let Some(record) = &recovery { content = add_recovery_record(&content, record.redundancy)?; }
+    replace_file_atomically(filepath, &content)?;
+
+    if signature.is_some() { println!("Embedded signature no longer matches and has been removed. Sign the file again."); }
+    for (index, slot) in table.slots.iter().enumerate() { println!("Slot {}: {}", index, slot.describe()); }
+    return Ok(());
+}
+
+fn split_data_key(filepath: &str, credential_spec: &str, conceal_arg: Option<&str>, threshold: u8, count: u8) -> io::Result<()>
+{
+    let (full_buffer, _) = read_file(filepath)?;
+    let (buffer, _) = split_signature_trailer(&full_buffer);
+    let (buffer, _) = check_recovery_record(buffer)?;
+    let revealed: Option<(Vec<u8>, RZ_KEY_TYPE, bool)> = reveal_for_edit(buffer, conceal_arg)?;
+    let buffer: &[u8] = revealed.as_ref().map_or(buffer, |(container, _, _)| container);
+    let table: KeySlotTable = parse_encoded_header(buffer)?.key_slots
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "File has no key slots, so it has no data key to split"))?;
+
+    let data_key: RZ_KEY_TYPE = table.unlock(&[Credential::from_spec(credential_spec)?])?;
+    let share_paths: Vec<String> = write_shares(filepath, &data_key, &table.key_hash, threshold, count)?;
+    println!("Any {} of these {} shares rebuild the data key:", threshold, count);
+    for share_path in share_paths { println!("  {}", share_path); }
+    return Ok(());
+}
+
+fn encode_stage(stage: &CodecList, data: &[u8], key: Option<&RZ_KEY_TYPE>) -> io::Result<(Vec<u8>, StageParams)>
+{
+    match stage
+    {
+        CodecList::Huffman => { return HuffmanEncoder::encode_with_params(data, key); }
+        CodecList::RLE => { return RLEEncoder::encode_with_params(data, key); }
+        CodecList::Caesar => { return CaesarEncoder::encode_with_params(data, key); }
+        CodecList::AES => { return AESEncoder::encode_with_params(data, key); }
+        CodecList::Vigenere => { return VigenereEncoder::encode_with_params(data, key); }
+        CodecList::XOR => { return XOREncoder::encode_with_params(data, key); }
+        _ => { return Err(Error::new(ErrorKind::InvalidData, format!("Codec {:?} cannot be run on its own", stage))); }
+    }
+}
+
+fn decode_stage(stage: &CodecList, encoded_data: &[u8], key: Option<&RZ_KEY_TYPE>, params: &[u8]) -> io::Result<Vec<u8>>
+{
+    match stage
+    {
+        CodecList::Huffman => { return HuffmanEncoder::decode_with_params(encoded_data, key, params); }
+        CodecList::RLE => { return RLEEncoder::decode_with_params(encoded_data, key, params); }
+        CodecList::Caesar => { return CaesarEncoder::decode_with_params(encoded_data, key, params); }
+        CodecList::AES => { return AESEncoder::decode_with_params(encoded_data, key, params); }
+        CodecList::Vigenere => { return VigenereEncoder::decode_with_params(encoded_data, key, params); }
+        CodecList::XOR => { return XOREncoder::decode_with_params(encoded_data, key, params); }
+        _ => { return Err(Error::new(ErrorKind::InvalidData, format!("Codec {:?} cannot be run on its own", stage))); }
+    }
+}
+
+fn chunk_stages(codecs: &[u8]) -> io::Result<Vec<CodecList>>
+{
+    // Recipient and key slot stages keep their keys in the header, so they cannot be repeated for every chunk
+    return codecs.iter()
+        .map(|&codec_byte| match CodecList::try_from(codec_byte)
+        {
+            Ok(stage @ (CodecList::X25519 | CodecList::KeySlots)) => Err(Error::new(ErrorKind::InvalidInput, format!("{:?} stages cannot be used in chunked or streamed files", stage))),
+            Ok(stage) => Ok(stage),
+            Err(_) => Err(Error::new(ErrorKind::InvalidData, "Invalid codec found")),
+        })
+        .collect();
+}
+
+fn chunk_encoder<'a>(codecs: &[u8], keys: &'a [RZ_KEY_TYPE]) -> io::Result<impl FnMut(&[u8]) -> io::Result<EncodedChunk> + 'a>
+{
+    let stages: Vec<CodecList> = chunk_stages(codecs)?;
+    let mut keys_iter = keys.iter();
+    let stage_keys: Vec<Option<&RZ_KEY_TYPE>> = stages.iter().map(|stage| if stage.needs_key() { keys_iter.next() } else { None }).collect();
+
+    return Ok(move |chunk: &[u8]| -> io::Result<EncodedChunk>
+    {
+        let mut chunk_buffer: Zeroizing<Vec<u8>> = Zeroizing::new(chunk.to_vec());
+        let mut chunk_params: Vec<StageParams> = Vec::with_capacity(stages.len());
+        for (stage, key) in stages.iter().zip(&stage_keys)
+        {
+            let (encoded_data, params) = encode_stage(stage, &chunk_buffer, *key)?;
+            wipe_and_replace(&mut chunk_buffer, encoded_data);
+            chunk_params.push(params);
+        }
+        return Ok((std::mem::take(&mut *chunk_buffer), chunk_params));
+    });
+}
+
+fn check_chunk_params(params: &[StageParams], stages_count: usize) -> io::Result<()>
+{
+    if params.len() != stages_count
+    {
+        return Err(Error::new(ErrorKind::InvalidData, format!("Chunk records parameters for {} stage(s), but the pipeline has {}", params.len(), stages_count)));
+    }
+    return Ok(());
+}
+
+fn encode_chunked(data: &[u8], codecs: &[u8], keys: &[RZ_KEY_TYPE], chunk_size: usize) -> io::Result<Vec<u8>>
+{
+    return frame_chunks(data, chunk_size, chunk_encoder(codecs, keys)?);
+}
+
+// Chunk size for standard input when none is given
+const STREAM_CHUNK_SIZE: usize = 1 << 20;
+
+fn encode_stream(codecs: &[u8], keys: &[RZ_KEY_TYPE], concealed: bool, header_options: &HeaderOptions, output_options: &OutputOptions) -> io::Result<()>
+{
+    // Standard input is read one chunk at a time and every chunk is written as soon as it is encoded,
+    // so nothing that needs the whole container (concealing, recovery records, volumes) can be used
+    if concealed || header_options.recovery.is_some() || header_options.volume_size.is_some()
+    {
+        return Err(Error::new(ErrorKind::InvalidInput, "Concealing, recovery records and volumes need the whole file, so they cannot be used with standard input"));
+    }
+    let chunk_size: usize = header_options.chunk_size.unwrap_or(STREAM_CHUNK_SIZE);
+    let mut encode_chunk = chunk_encoder(codecs, keys)?;
+
+    // The checksum trails the stream; the header only names its algorithm
+    let fields: Vec<HeaderField> = header_options.checksum
+        .map(|algorithm| HeaderField { kind: FIELD_STREAMED_CHECKSUM, value: vec![algorithm as u8] }).into_iter().collect();
+    let mut checksum: Option<StreamingChecksum> = header_options.checksum.map(StreamingChecksum::new);
+
+    let mut input = io::stdin().lock();
+    let mut output: OutputFile = OutputFile::create(output_options.path.as_deref().unwrap_or(STDIO_PATH), output_options.force)?;
+    // Every frame records its own stage parameters, so the header keeps empty ones
+    output.write_all(&serialize_encoded_file(&[], FLAG_STREAMED, codecs, &vec![StageParams::new(); codecs.len()], None, &fields))?;
+    let mut original_len: u64 = 0;
+    loop
+    {
+        let mut chunk: Zeroizing<Vec<u8>> = Zeroizing::new(Vec::with_capacity(chunk_size));
+        (&mut input).take(chunk_size as u64).read_to_end(&mut chunk)?;
+        if chunk.is_empty() { break; }
+
+        let (encoded_data, chunk_params) = encode_chunk(&chunk)?;
+        write_frame(&mut output, &chunk, &encoded_data, &chunk_params)?;
+        if let Some(checksum) = &mut checksum { checksum.update(&chunk); }
+        original_len += chunk.len() as u64;
+    }
+
+    let checksum_record: Vec<u8> = checksum.map(|checksum| checksum.field().value).unwrap_or_default();
+    write_stream_end(&mut output, original_len, &checksum_record)?;
+    return output.commit();
+}
+
+// Decoded chunk, before it is checked against its entry
+type DecodedChunk = io::Result<Vec<u8>>;
+
+fn chunk_decoder<'a>(codecs: &[u8], keys: &'a [RZ_KEY_TYPE]) -> io::Result<impl FnMut(&[u8], &[StageParams]) -> DecodedChunk + 'a>
+{
+    // Keys are given as for whole files: the first one belongs to the outermost keyed stage
+    let stages: Vec<CodecList> = chunk_stages(codecs)?;
+    let mut keys_iter = keys.iter();
+    let mut stage_keys: Vec<Option<&RZ_KEY_TYPE>> = Vec::with_capacity(stages.len());
+    for stage in stages.iter().rev()
+    {
+        if !stage.needs_key() { stage_keys.push(None); continue; }
+        let key: &RZ_KEY_TYPE = keys_iter.next().ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Not enough keys for the encrypted stages"))?;
+        stage_keys.push(Some(key));
+    }
+    stage_keys.reverse();
+
+    return Ok(move |chunk: &[u8], chunk_params: &[StageParams]| -> DecodedChunk
+    {
+        check_chunk_params(chunk_params, stages.len())?;
+        let mut chunk_buffer: Zeroizing<Vec<u8>> = Zeroizing::new(chunk.to_vec());
+        for ((stage, key), params) in stages.iter().zip(&stage_keys).zip(chunk_params).rev()
+        {
+            let decoded_data: Vec<u8> = decode_stage(stage, &chunk_buffer, *key, params)?;
+            wipe_and_replace(&mut chunk_buffer, decoded_data);
+        }
+        return Ok(std::mem::take(&mut *chunk_buffer));
+    });
+}
+
+fn range_file(filepath: &str, (start, end): (u64, Option<u64>), keys: &[RZ_KEY_TYPE]) -> io::Result<()>
+{
+    // Only the header, the seek table and the chunks in range are read, never the whole file
+    let mut file: File = File::open(filepath)?;
+    let file_len: u64 = file.metadata()?.len();
+    let mut fixed_header: Vec<u8> = Vec::with_capacity(16);
+    (&mut file).take(16).read_to_end(&mut fixed_header)?;
+    if fixed_header.len() < 16 || !fixed_header.starts_with(FORMAT_MAGIC) { parse_fixed_header(&fixed_header)?; }
+    let header_len: u64 = u32::from_be_bytes(fixed_header[12..16].try_into().expect("Header length takes 4 bytes")) as u64;
+    if header_len > file_len { return Err(Error::new(ErrorKind::InvalidData, "File header is truncated")); }
+
+    let mut header: Vec<u8> = vec![0u8; header_len as usize];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)?;
+    let EncodedHeader { flags, codecs, payload_offset, .. } = parse_encoded_header(&header)?;
+    if flags & FLAG_CHUNKED == 0 { return Err(Error::new(ErrorKind::InvalidInput, "File is not chunked; encode it with --chunk-size to read ranges")); }
+
+    // An embedded signature trails the payload; it is not checked, as that would read the whole file
+    let mut payload_end: u64 = file_len;
+    if file_len >= header_len + TRAILER_LEN as u64
+    {
+        let mut trailer_magic: [u8; 8] = [0u8; 8];
+        file.seek(SeekFrom::Start(file_len - trailer_magic.len() as u64))?;
+        file.read_exact(&mut trailer_magic)?;
+        if &trailer_magic == TRAILER_MAGIC
+        {
+            payload_end -= TRAILER_LEN as u64;
+            eprintln!("Embedded signature is not checked when reading a range.");
+        }
+    }
+    if payload_end >= header_len + RECOVERY_FOOTER_LEN as u64
+    {
+        let mut recovery_footer: [u8; RECOVERY_FOOTER_LEN] = [0u8; RECOVERY_FOOTER_LEN];
+        file.seek(SeekFrom::Start(payload_end - RECOVERY_FOOTER_LEN as u64))?;
+        file.read_exact(&mut recovery_footer)?;
+        if let Some(record) = RecoveryRecord::from_footer(&recovery_footer)? { payload_end = record.container_len as u64; }
+    }
+
+    let decode_chunk = chunk_decoder(&codecs, keys)?;
+    let range: Zeroizing<Vec<u8>> = Zeroizing::new(read_range(&mut file, payload_offset as u64, payload_end, start, end, decode_chunk)?);
+    io::stdout().write_all(&range)?;
+    return Ok(());
+}
+
+fn rekey_file(filepath: &str, old_key_args: &[Zeroizing<String>], new_key_args: &[Zeroizing<String>]) -> io::Result<()>
+{
+    let old_keys: Vec<RZ_KEY_TYPE> = old_key_args.iter().enumerate()
+        .map(|(i, arg)| resolve_key(arg, &format!("Old key {}", i + 1), false))
+        .collect::<io::Result<Vec<_>>>()?;
+    let new_keys: Vec<RZ_KEY_TYPE> = new_key_args.iter().enumerate()
+        .map(|(i, arg)| resolve_key(arg, &format!("New key {}", i + 1), true))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let (full_buffer, _) = read_file(filepath)?;
+    let (buffer, signature) = split_signature_trailer(&full_buffer);
+    let (mut buffer, recovery) = check_recovery_record(buffer)?;
+
+    // A concealed header takes the first old key to open and the first new key to seal again
+    let mut old_keys: &[RZ_KEY_TYPE] = &old_keys;
+    let mut new_keys: &[RZ_KEY_TYPE] = &new_keys;
+    let mut concealed: Option<(&RZ_KEY_TYPE, bool)> = None;
+    let revealed: Vec<u8>;
+    if is_concealed(buffer)
+    {
+        let (old_conceal_key, new_conceal_key) = old_keys.first().zip(new_keys.first())
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "File header is concealed, so its old and new keys are needed first"))?;
+        let (container, padded) = reveal_container(buffer, old_conceal_key)?;
+        revealed = container;
+        buffer = &revealed;
+        concealed = Some((new_conceal_key, padded));
+        old_keys = &old_keys[1..];
+        new_keys = &new_keys[1..];
+    }
+
+    let EncodedHeader { flags, codecs, mut stage_params, key_slots, fields, payload_offset } = parse_encoded_header(buffer)?;
+
+    let stages: Vec<CodecList> = codecs.iter()
+        .map(|&codec_byte| CodecList::try_from(codec_byte).map_err(|_| Error::new(ErrorKind::InvalidData, "Invalid codec found")))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    // Only stages from the innermost password-keyed one outwards are re-run; everything below it stays as stored
+    let first_keyed: usize = match stages.iter().position(CodecList::needs_key)
+    {
+        Some(first_keyed) => first_keyed,
+        None if concealed.is_some() => stages.len(),
+        None => { return Err(Error::new(ErrorKind::InvalidInput, "File has no password-encrypted stage to rekey")); }
+    };
+    let rerun_stages: &[CodecList] = &stages[first_keyed..];
+    if rerun_stages.iter().any(|stage| matches!(stage, CodecList::X25519 | CodecList::KeySlots))
+    {
+        return Err(Error::new(ErrorKind::InvalidInput, "Recipient and key slot stages cannot be rekeyed; manage their key slots instead"));
+    }
+
+    let keyed_count: usize = rerun_stages.iter().filter(|stage| stage.needs_key()).count();
+    if old_keys.len() != keyed_count || new_keys.len() != keyed_count
+    {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("File has {} encrypted stages, so {} old and {} new keys are needed", keyed_count, keyed_count, keyed_count)));
+    }
+
+    // Whole files are recoded with the header's parameters, chunks with those of their own entry
+    let recode = |stage_data: &[u8], old_params: &[StageParams]| -> io::Result<EncodedChunk>
+    {
+        check_chunk_params(old_params, stages.len())?;
+        // Keys are given as for decoding: the first one belongs to the outermost keyed stage
+        let mut stage_buffer: Zeroizing<Vec<u8>> = Zeroizing::new(stage_data.to_vec());
+        let mut i_current_key: usize = 0;
+        for (stage, params) in rerun_stages.iter().zip(&old_params[first_keyed..]).rev()
+        {
+            let key: Option<&RZ_KEY_TYPE> = if stage.needs_key() { i_current_key += 1; Some(&old_keys[i_current_key - 1]) } else { None };
+            let decoded_data: Vec<u8> = decode_stage(stage, &stage_buffer, key, params)?;
+            wipe_and_replace(&mut stage_buffer, decoded_data);
+        }
+
+        // Rebuilds the same stages, from the innermost one, with the new keys
+        let mut new_params: Vec<StageParams> = old_params[..first_keyed].to_vec();
+        for stage in rerun_stages
+        {
+            let key: Option<&RZ_KEY_TYPE> = if stage.needs_key() { i_current_key -= 1; Some(&new_keys[i_current_key]) } else { None };
+            let (encoded_data, params) = encode_stage(stage, &stage_buffer, key)?;
+            wipe_and_replace(&mut stage_buffer, encoded_data);
+            new_params.push(params);
+        }
+        return Ok((std::mem::take(&mut *stage_buffer), new_params));
+    };
+    let payload: Vec<u8> = if flags & FLAG_CHUNKED != 0 { map_chunks(&buffer[payload_offset..], recode)? }
+        else if flags & FLAG_STREAMED != 0 { map_frames(&buffer[payload_offset..], recode)? }
+        else
+        {
+            let (payload, new_params) = recode(&buffer[payload_offset..], &stage_params)?;
+            stage_params = new_params;
+            payload
+        };
+
+    // Pipeline does not change, but re-run stages may have recorded new parameters
+    let mut content: Vec<u8> = serialize_encoded_file(&payload, flags, &codecs, &stage_params, key_slots.as_ref(), &fields);
+    if let Some((conceal_key, pad)) = concealed { content = conceal_container(&content, conceal_key, pad)?; }
+    if let Some(record) = &recovery { content = add_recovery_record(&content, record.redundancy)?; }
+    replace_file_atomically(filepath, &content)?;
+
+    if signature.is_some() { println!("Embedded signature no longer matches and has been removed. Sign the file again."); }
+    return Ok(());
+}
+
+fn repair_file(filepath: &str) -> io::Result<()>
+{
+    let (full_buffer, _) = read_file(filepath)?;
+    let (buffer, _) = split_signature_trailer(&full_buffer);
+    let (_, recovery) = split_recovery_record(buffer)?;
+    let record: RecoveryRecord = recovery
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "File has no recovery record; encode it with --recovery <percent>"))?;
+
+    let damaged: Vec<usize> = record.damaged_shards(buffer);
+    if damaged.is_empty() && !record.footer_damaged
+    {
+        println!("No damaged shards found; nothing to repair.");
+        return Ok(());
+    }
+
+    // Parity, checksums and both footers are rebuilt from the repaired container; an embedded signature is kept as it was, and matches again
+    let container: Vec<u8> = if damaged.is_empty() { buffer[..record.container_len].to_vec() } else { record.repair(buffer, &damaged)? };
+    let record_end: usize = record.container_len + record.record_len();
+    let mut content: Vec<u8> = add_recovery_record(&container, record.redundancy)?;
+    content.extend_from_slice(&full_buffer[record_end.min(buffer.len())..]);
+    replace_file_atomically(filepath, &content)?;
+    if record.footer_damaged { println!("Damaged recovery record footer rewritten."); }
+    if !damaged.is_empty() { println!("{} damaged shard(s) rebuilt: {:?}.", damaged.len(), damaged); }
+    return Ok(());
+}
+
+fn is_known_field(kind: u8) -> bool
+{
+    return matches!(kind, FIELD_CHECKSUM | FIELD_STREAMED_CHECKSUM | FIELD_FILE_NAME | FIELD_FILE_MODE | FIELD_FILE_MTIME | FIELD_FILE_XATTR);
+}
+
+fn info_file(filepath: &str) -> io::Result<()>
+{
+    // Everything shown comes from the header and trailer; no key is asked for and no stage is decoded
+    let filepath: &str = volume_base(filepath);
+    let (full_buffer, file_len) = read_encoded_file(filepath)?;
+    let (signed_content, signature) = split_signature_trailer(&full_buffer);
+    let (buffer, recovery) = split_recovery_record(signed_content)?;
+    let damaged: usize = recovery.as_ref().map_or(0, |record| record.damaged_shards(signed_content).len());
+    if damaged > 0 { eprintln!("Warning: {} shard(s) are damaged, so what follows may be wrong; run `RustyZipper repair {}` first.", damaged, filepath); }
+    let FixedHeader { version: (major_version, minor_version), flags, header_len, .. } = parse_fixed_header(buffer)?;
+
+    println!("File:            {}", filepath);
+    println!("Format version:  {}.{}", major_version, minor_version);
+    match signature
+    {
+        Some((_, signer)) => { println!("Signature:       embedded (signer {})", to_hex(&signer)); }
+        None => { println!("Signature:       none embedded"); }
+    }
+    match &recovery
+    {
+        Some(record) =>
+        {
+            let status: String = match (damaged, record.footer_damaged)
+            {
+                (0, false) => String::from("intact"),
+                (0, true) => format!("one of its two footers damaged, run `RustyZipper repair {}`", filepath),
+                _ => format!("{} damaged shard(s), run `RustyZipper repair {}`", damaged, filepath),
+            };
+            println!("Recovery record: {}, {}", record.describe(), status);
+        }
+        None => { println!("Recovery record: none"); }
+    }
+    if is_concealed(buffer)
+    {
+        println!("Header:          concealed; pipeline, sizes and metadata need the concealing key");
+        println!("Encoded size:    {} bytes", file_len);
+        return Ok(());
+    }
+
+    let EncodedHeader { codecs, stage_params, key_slots, fields, .. } = parse_encoded_header(buffer)?;
+    println!("Flags:           {:#06x}", flags);
+    if flags & FLAG_CHUNKED != 0
+    {
+        let (entries, _) = seek_table(&buffer[header_len..])?;
+        let chunk_size: u64 = entries.first().map(|entry| entry.original_len).unwrap_or(0);
+        println!("Chunks:          {} of up to {} bytes, with a seek table", entries.len(), chunk_size);
+    }
+    let mut streamed_len: Option<u64> = None;
+    if flags & FLAG_STREAMED != 0
+    {
+        let (chunks_count, original_len, _) = stream_frames(&buffer[header_len..])?;
+        println!("Chunks:          {}, streamed without a seek table", chunks_count);
+        streamed_len = Some(original_len);
+    }
+    println!("Pipeline:        {} stage(s), applied in this order when encoding", codecs.len());
+    for (i_stage, (&codec_byte, params)) in codecs.iter().zip(&stage_params).enumerate()
+    {
+        let (name, needs): (String, &str) = match CodecList::try_from(codec_byte)
+        {
+            Ok(stage) if stage.needs_key() => (format!("{:?}", stage), "needs a key"),
+            Ok(stage @ CodecList::X25519) => (format!("{:?}", stage), "needs a recipient identity"),
+            Ok(stage @ CodecList::KeySlots) => (format!("{:?}", stage), "needs a key slot credential or shares"),
+            Ok(stage) => (format!("{:?}", stage), "no key"),
+            Err(_) => (format!("unknown codec {}", codec_byte), "unknown"),
+        };
+        let params_text: String = if flags & (FLAG_CHUNKED | FLAG_STREAMED) != 0 { String::from("stored with every chunk") }
+            else if params.is_empty() { String::from("none") } else { to_hex(params) };
+        println!("  {}. {:<10} {:<40} parameters: {}", i_stage + 1, name, needs, params_text);
+    }
+    if let Some(table) = &key_slots
+    {
+        for (index, slot) in table.slots.iter().enumerate() { println!("  Key slot {}: {}", index, slot.describe()); }
+    }
+
+    // Streamed files keep their length in the end frame and only name the checksum in the header
+    let (original_len, checksum): (Option<u64>, Option<ChecksumAlgorithm>) = match (stored_checksum(&fields)?, streamed_len)
+    {
+        (Some((algorithm, original_len)), _) => (Some(original_len), Some(algorithm)),
+        (None, Some(original_len)) => (Some(original_len), streamed_checksum(&fields)?),
+        (None, None) => (None, None),
+    };
+    match original_len
+    {
+        Some(original_len) => { println!("Original size:   {} bytes", original_len); }
+        None => { println!("Original size:   unknown"); }
+    }
+    println!("Encoded size:    {} bytes ({} header, {} payload)", file_len, header_len, buffer.len() - header_len);
+    if let Some(original_len) = original_len.filter(|&original_len| original_len > 0) { println!("Ratio:           {:.2}%", 100.0 * file_len as f64 / original_len as f64); }
+    match checksum
+    {
+        Some(algorithm) => { println!("Checksum:        {}", algorithm.name()); }
+        None => { println!("Checksum:        none"); }
+    }
+
+    let metadata: FileMetadata = FileMetadata::from_fields(&fields);
+    if let Some(name) = &metadata.name { println!("Original name:   {}", name); }
+    if let Some(mode) = metadata.mode { println!("Mode:            {:04o}", mode); }
+    if let Some((seconds, _)) = metadata.mtime { println!("Modified:        {}", format_unix_time(seconds)); }
+    for (name, value) in &metadata.xattrs { println!("Xattr:           {} ({} bytes)", String::from_utf8_lossy(name), value.len()); }
+
+    let unknown_fields: usize = fields.iter().filter(|field| !is_known_field(field.kind)).count();
+    if unknown_fields > 0 { println!("Unknown fields:  {} optional field(s) skipped", unknown_fields); }
+    return Ok(());
+}
+
+// Candidate shifts whose RLE stage would expand beyond this are discarded without decoding
+const ATTACK_MAX_EXPANSION: u64 = 1 << 30;
+
+fn attack_file(filepath: &str, reference: Option<&str>) -> io::Result<()>
+{
+    let (full_buffer, _) = read_file(filepath)?;
+    let (buffer, _) = split_signature_trailer(&full_buffer);
+    let (buffer, _) = check_recovery_record(buffer)?;
+    let EncodedHeader { flags, codecs, stage_params, payload_offset, .. } = parse_encoded_header(buffer)?;
+    if flags & FLAG_CHUNKED != 0 { return Err(Error::new(ErrorKind::InvalidInput, "Chunked files cannot be attacked; every chunk has its own Caesar stage")); }
+    if flags & FLAG_STREAMED != 0 { return Err(Error::new(ErrorKind::InvalidInput, "Streamed files cannot be attacked; every frame has its own Caesar stage")); }
+
+    let model: ReferenceModel = match reference
+    {
+        Some(corpus_path) => ReferenceModel::from_corpus(&read_file(corpus_path)?.0)?,
+        None => ReferenceModel::English,
+    };
+
+    let stages: Vec<CodecList> = codecs.iter()
+        .map(|&codec_byte| CodecList::try_from(codec_byte).map_err(|_| Error::new(ErrorKind::InvalidData, "Invalid codec found")))
+        .collect::<io::Result<Vec<_>>>()?;
+    let caesar_stage: usize = stages.iter().rposition(|stage| matches!(stage, CodecList::Caesar))
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "File has no Caesar stage"))?;
+
+    // Stages wrapping the Caesar one can only be peeled when they need no key
+    let mut stage_buffer: Vec<u8> = buffer[payload_offset..].to_vec();
+    for (stage, params) in stages[caesar_stage + 1..].iter().zip(&stage_params[caesar_stage + 1..]).rev()
+    {
+        if stage.needs_key() || matches!(stage, CodecList::X25519 | CodecList::KeySlots)
+        {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("Caesar stage is wrapped by a {:?} stage, which needs a key", stage)));
+        }
+        stage_buffer = decode_stage(stage, &stage_buffer, None, params)?;
+    }
+    if stage_buffer.len() < 32 { return Err(Error::new(ErrorKind::InvalidData, "Caesar stage is too short")); }
+
+    // Candidates are scored on the final output, so the stages below Caesar must be keyless to be decoded too
+    let inner_stages: &[CodecList] = &stages[..caesar_stage];
+    let inner_params: &[StageParams] = &stage_params[..caesar_stage];
+    if let Some(stage) = inner_stages.iter().rev().find(|stage| stage.needs_key() || matches!(stage, CodecList::X25519 | CodecList::KeySlots))
+    {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("Caesar stage wraps a {:?} stage, which needs a key, so candidates cannot be scored", stage)));
+    }
+    let decode_inner = |unshifted: &[u8]| -> io::Result<Vec<u8>>
+    {
+        let mut inner_buffer: Vec<u8> = unshifted.to_vec();
+        for (stage, params) in inner_stages.iter().zip(inner_params).rev()
+        {
+            // A wrong shift turns RLE counts into garbage, which could expand to terabytes
+            if matches!(stage, CodecList::RLE) && RLEEncoder::expanded_len(&inner_buffer) > ATTACK_MAX_EXPANSION
+            {
+                return Err(Error::new(ErrorKind::InvalidData, "Implausible RLE expansion"));
+            }
+            inner_buffer = decode_stage(stage, &inner_buffer, None, params)?;
+        }
+        return Ok(inner_buffer);
+    };
+
+    let candidates: Vec<ShiftCandidate> = break_caesar(&stage_buffer[32..], &model, decode_inner);
+    let best: &ShiftCandidate = candidates.first()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "No shift gave content that could be scored: the stage is empty, or the stages below it rejected every shift"))?;
+    println!("Caesar stage {} of {}: most likely shifts", caesar_stage + 1, stages.len());
+    for (rank, candidate) in candidates.iter().take(5).enumerate()
+    {
+        println!("  {}. shift {:>3} (last key byte 0x{:02x})  chi-squared {:>12.2}  confidence {:>6.2}%  \"{}\"",
+            rank + 1, candidate.shift, candidate.shift, candidate.chi_squared, candidate.confidence * 100.0, candidate.preview);
+    }
+    println!("Caesar only uses the last key byte, so if shift {} is right, any key ending in byte 0x{:02x} decrypts the stage; the stored key hash does not stop it.", best.shift, best.shift);
+    return Ok(());
+}
+
+fn bench_pipeline(data: &[u8], stages: &[CodecList], runs: u32) -> io::Result<BenchOutcome>
+{
+    if let Some(stage) = stages.iter().find(|stage| matches!(stage, CodecList::LZ77 | CodecList::Arithmetic))
+    {
+        return Ok(BenchOutcome::Unavailable(format!("{:?} is not implemented", stage)));
+    }
+
+    // Keyed stages run with random keys, as their speed does not depend on the key
+    let mut keys: Vec<Option<RZ_KEY_TYPE>> = Vec::with_capacity(stages.len());
+    for stage in stages { keys.push(if stage.needs_key() { Some(RZ_KEY_TYPE::random()?) } else { None }); }
+
+    let (mut encode_time, mut decode_time, mut peak_memory, mut encoded_len) = (Duration::MAX, Duration::MAX, None, 0);
+    for _ in 0..runs
+    {
+        // The copy of the input is made before measuring, so the peak is what the pipeline itself allocates
+        let mut buffer: Vec<u8> = data.to_vec();
+        let memory: PeakMemory = PeakMemory::start();
+        let mut stage_params: Vec<StageParams> = Vec::with_capacity(stages.len());
+        let start: Instant = Instant::now();
+        for (stage, key) in stages.iter().zip(&keys)
+        {
+            let (encoded_data, params) = encode_stage(stage, &buffer, key.as_ref())?;
+            buffer = encoded_data;
+            stage_params.push(params);
+        }
+        encode_time = encode_time.min(start.elapsed());
+        encoded_len = buffer.len();
+
+        let start: Instant = Instant::now();
+        for ((stage, key), params) in stages.iter().zip(&keys).zip(&stage_params).rev()
+        {
+            buffer = decode_stage(stage, &buffer, key.as_ref(), params)?;
+        }
+        decode_time = decode_time.min(start.elapsed());
+        peak_memory = peak_memory.max(memory.peak());
+        if buffer != data { return Err(Error::new(ErrorKind::InvalidData, "Decoded content does not match the original")); }
+    }
+    return Ok(BenchOutcome::Measured { encoded_len, encode_time, decode_time, peak_memory });
+}
+
+fn bench_files(filepaths: &[String], pipelines: &[BenchPipeline], runs: u32) -> io::Result<()>
+{
+    // Every pipeline runs on every file even after a failure; failures are shown in the table
+    let mut rows: Vec<BenchRow> = Vec::new();
+    let mut failed: bool = false;
+    for filepath in filepaths
+    {
+        let data: Vec<u8> = match read_file(filepath)
+        {
+            Ok((data, _)) => data,
+            Err(e) =>
+            {
+                eprintln!("{} could not be read: {}", filepath, e);
+                failed = true;
+                continue;
+            }
+        };
+        for pipeline in pipelines
+        {
+            let outcome: BenchOutcome = bench_pipeline(&data, &pipeline.0, runs).unwrap_or_else(|e| BenchOutcome::Failed(e.to_string()));
+            failed |= matches!(outcome, BenchOutcome::Failed(_));
+            rows.push(BenchRow { file: filepath.clone(), pipeline: pipeline.describe(), original_len: data.len(), outcome });
+        }
+    }
+
+    print_table(&rows);
+    if failed { return Err(Error::other("Some pipelines could not be benchmarked")); }
+    return Ok(());
+}
+
+fn main() -> io::Result<()>
+{
+    let result: io::Result<()> = match CliHandling::parse_cli().command
+    {
+        Command::Compress(args) =>
+        {
+            // encode_file and run_batch report their own errors
+            let (header_options, output_options) = (args.header_options(), args.output_options());
+            match args.plan().inspect_err(|e| eprintln!("{}", e))
+            {
+                Ok(EncodePlan { codecs, keys, conceal, .. }) if args.is_stdin() =>
+                {
+                    encode_stream(&codecs, &keys, conceal.is_some(), &header_options, &output_options)
+                        .inspect_err(|e| eprintln!("Standard input could not be encoded due to an error: {}", e))
+                }
+                Ok(plan) =>
+                {
+                    run_batch(&args.files, &args.batch_options(), BatchInput::Plain, output_options.path.as_deref(),
+                        |filepath| encode_file(filepath, &plan, args.pad, &header_options, &output_options))
+                }
+                Err(e) => Err(e),
+            }
+        }
+        Command::Decompress(args) =>
+        {
+            let output_options: OutputOptions = args.output_options();
+            match args.credentials.plan().inspect_err(|e| eprintln!("{}", e))
+            {
+                Ok(plan) if args.is_stdin() =>
+                {
+                    decode_stream(&plan, &output_options, args.ignore_checksum)
+                        .inspect_err(|e| eprintln!("Standard input could not be decoded due to an error: {}", e))
+                }
+                Ok(plan) =>
+                {
+                    run_batch(&args.files, &args.batch_options(), BatchInput::Encoded, output_options.path.as_deref(),
+                        |filepath| decode_file(filepath, &plan, &output_options, args.ignore_checksum, !args.no_metadata))
+                }
+                Err(e) => Err(e),
+            }
+        }
+        Command::Keygen(args) =>
+        {
+            let keypair = if args.ed25519 { generate_signing_keypair(&args.name) } else { RecipientEncoder::generate_keypair(&args.name) };
+            match keypair
+            {
+                Ok((public_path, private_path)) =>
+                {
+                    println!("Public key saved to {} and private key saved to {}.", public_path, private_path);
+                    Ok(())
+                }
+                Err(e) =>
+                {
+                    eprintln!("Keypair could not be generated due to an error: {}", e);
+                    Err(e)
+                }
+            }
+        }
+        Command::Sign(args) =>
+        {
+            match sign_file(&args.file, &args.private_key, args.detached)
+            {
+                Ok(()) =>
+                {
+                    println!("File signed.");
+                    Ok(())
+                }
+                Err(e) =>
+                {
+                    eprintln!("File could not be signed due to an error: {}", e);
+                    Err(e)
+                }
+            }
+        }
+        Command::Verify(args) =>
+        {
+            match verify_file(&args.file, &args.public_key)
+            {
+                Ok(()) =>
+                {
+                    println!("Signature is valid.");
+                    Ok(())
+                }
+                Err(e) =>
+                {
+                    eprintln!("Verification failed: {}", e);
+                    Err(e)
+                }
+            }
+        }
+        Command::Rekey(args) =>
+        {
+            match rekey_file(&args.file, &args.old_keys, &args.new_keys)
+            {
+                Ok(()) =>
+                {
+                    println!("File rekeyed.");
+                    Ok(())
+                }
+                Err(e) =>
+                {
+                    eprintln!("File could not be rekeyed due to an error: {}", e);
+                    Err(e)
+                }
+            }
+        }
+        Command::Attack(args) =>
+        {
+            attack_file(&args.file, args.reference.as_deref())
+                .inspect_err(|e| eprintln!("Attack could not be run due to an error: {}", e))
+        }
+        Command::AddSlot(args) =>
+        {
+            modify_key_slots(&args.file, &args.unlock, args.conceal.as_deref().map(String::as_str), |table, data_key| table.add_slot(&args.slot, data_key))
+                .inspect_err(|e| eprintln!("Key slot could not be added due to an error: {}", e))
+        }
+        Command::RemoveSlot(args) =>
+        {
+            modify_key_slots(&args.file, &args.unlock, args.conceal.as_deref().map(String::as_str), |table, _| table.remove_slot(args.index))
+                .inspect_err(|e| eprintln!("Key slot could not be removed due to an error: {}", e))
+        }
+        Command::Test(args) =>
+        {
+            // test_file and run_batch report their own errors
+            match args.credentials.plan().inspect_err(|e| eprintln!("{}", e))
+            {
+                Ok(plan) => run_batch(&args.files, &args.batch_options(), BatchInput::Encoded, None, |filepath| test_file(filepath, &plan)),
+                Err(e) => Err(e),
+            }
+        }
+        Command::Bench(args) => bench_files(&args.files, &args.pipelines, args.runs),
+        Command::Info(args) => info_file(&args.file).inspect_err(|e| eprintln!("File could not be inspected due to an error: {}", e)),
+        Command::Repair(args) => repair_file(&args.file).inspect_err(|e| eprintln!("File could not be repaired due to an error: {}", e)),
+        Command::Range(args) =>
+        {
+            read_keys(&args.keys).and_then(|keys| range_file(&args.file, args.range, &keys))
+                .inspect_err(|e| eprintln!("Range could not be decoded due to an error: {}", e))
+        }
+        Command::SplitKey(args) =>
+        {
+            split_data_key(&args.file, &args.unlock, args.conceal.as_deref().map(String::as_str), args.threshold, args.shares)
+                .inspect_err(|e| eprintln!("Data key could not be split due to an error: {}", e))
+        }
+    };
+
+    if result.is_err() { std::process::exit(1); }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::KeyHandling::key_from_bytes;
+
+    fn temp_path(name: &str) -> String
+    {
+        return std::env::temp_dir().join(format!("rustyzipper-{}-{}", std::process::id(), name)).to_string_lossy().into_owned();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rekey_keeps_file_mode()
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let filepath: String = temp_path("rekey-mode.rsz");
+        let (payload, params) = encode_stage(&CodecList::AES, b"private content", Some(&key_from_bytes(b"old"))).expect("Encoding succeeds");
+        std::fs::write(&filepath, serialize_encoded_file(&payload, 0, &[CodecList::AES as u8], &[params], None, &[])).expect("Archive is written");
+        std::fs::set_permissions(&filepath, std::fs::Permissions::from_mode(0o600)).expect("Mode is set");
+
+        let result: io::Result<()> = rekey_file(&filepath, &[Zeroizing::new(String::from("old"))], &[Zeroizing::new(String::from("new"))]);
+        let mode: u32 = std::fs::metadata(&filepath).expect("Archive still exists").permissions().mode() & 0o777;
+        std::fs::remove_file(&filepath).ok();
+        result.expect("Rekeying succeeds");
+        assert_eq!(mode, 0o600);
+    }
+
+    #[test]
+    fn key_slots_change_on_concealed_file()
+    {
+        let filepath: String = temp_path("concealed-slots.rsz");
+        let (payload, table) = KeySlotEncoder::encode(b"slot content", &[Zeroizing::new(String::from("pw:alpha"))]).expect("Encoding succeeds");
+        let container: Vec<u8> = serialize_encoded_file(&payload, 0, &[CodecList::KeySlots as u8], &[StageParams::new()], Some(&table), &[]);
+        std::fs::write(&filepath, conceal_container(&container, &key_from_bytes(b"veil"), false).expect("Concealing succeeds")).expect("Archive is written");
+
+        let result: io::Result<()> = modify_key_slots(&filepath, "pw:alpha", Some("veil"), |table, data_key| table.add_slot("pw:beta", data_key))
+            .and_then(|_| split_data_key(&filepath, "pw:beta", Some("veil"), 2, 3));
+        let plan: DecodePlan = DecodePlan { keys: vec![key_from_bytes(b"veil"), key_from_bytes(b"beta")], identities: Vec::new(), shares: Vec::new(), signer: None };
+        let decoded: io::Result<(Zeroizing<Vec<u8>>, Vec<HeaderField>)> = read_file(&filepath).and_then(|(buffer, _)| decode_container(buffer, &plan));
+        let still_concealed: bool = read_file(&filepath).map(|(buffer, _)| is_concealed(&buffer)).unwrap_or(false);
+        let shares_written: bool = (1..=3).all(|share| std::fs::remove_file(format!("{}.share{}", filepath, share)).is_ok());
+        std::fs::remove_file(&filepath).ok();
+
+        result.expect("Slot is added and the key split");
+        assert_eq!(decoded.expect("New slot opens the file").0.as_slice(), b"slot content");
+        assert!(still_concealed);
+        assert!(shares_written);
+    }
+}